@@ -0,0 +1,64 @@
+use brawllib_rs::fighter::Fighter as BrawlFighter;
+use brawllib_rs::sakurai::fighter_data::FighterAttributes;
+
+use crate::settings::FighterSettings;
+use crate::warnings::Warnings;
+
+/// Air jump velocities in the absolute form PF Sandbox uses
+pub(crate) struct AirJump {
+    pub x_vel: f32,
+    pub y_vel: f32,
+}
+
+/// Brawl stores air jump velocities as multipliers of the grounded jump velocities.
+///
+/// Fighters with multiple air jumps (Kirby, Meta Knight, Jigglypuff, Pit etc.) have a multi jump section in their fighter data
+/// giving the y velocity of each air jump, the fighters `air_jump_y_mults` setting overrides these.
+/// PF Sandbox only supports a single air jump velocity so we use the velocity that,
+/// when used for every air jump, reaches the same total height as the individual jumps.
+pub(crate) fn air_jump(brawl_fighter: &BrawlFighter, attributes: &FighterAttributes, settings: &FighterSettings, warnings: &mut Warnings) -> AirJump {
+    let air_jumps = (attributes.num_jumps as usize).saturating_sub(1);
+
+    let x_vel = attributes.jump_x_init_vel * attributes.air_jump_x_mult;
+
+    let y_vels: Vec<f32> = if !settings.air_jump_y_mults.is_empty() {
+        if settings.air_jump_y_mults.len() != air_jumps {
+            warnings.fighter(format!("air_jump_y_mults setting has {} values but the fighter has {} air jumps", settings.air_jump_y_mults.len(), air_jumps));
+        }
+        settings.air_jump_y_mults.iter().map(|x| attributes.jump_y_init_vel * x).collect()
+    } else {
+        let multi_jump_y_vels = multi_jump_y_vels(brawl_fighter);
+        if multi_jump_y_vels.is_empty() {
+            // Fighters without air jumps still get the velocity they would have, so it is sensible if air jumps are added in PF Sandbox
+            vec!(attributes.jump_y_init_vel * attributes.air_jump_y_mult; air_jumps.max(1))
+        } else {
+            if multi_jump_y_vels.len() != air_jumps {
+                warnings.fighter(format!("multi jump data has {} jumps but the fighter has {} air jumps", multi_jump_y_vels.len(), air_jumps));
+            }
+            multi_jump_y_vels
+        }
+    };
+
+    let gravity = attributes.gravity;
+    let y_vel = if gravity > 0.0 && !y_vels.is_empty() {
+        // The height reached by a jump with initial velocity v and constant gravity g is v^2 / 2g
+        let total_height: f32 = y_vels.iter().map(|v| v * v / (2.0 * gravity)).sum();
+        let average_height = total_height / y_vels.len() as f32;
+        (2.0 * gravity * average_height).sqrt()
+    } else {
+        y_vels.get(0).cloned().unwrap_or(attributes.jump_y_init_vel * attributes.air_jump_y_mult)
+    };
+
+    AirJump {
+        x_vel: settings.air_jump_x_vel.unwrap_or(x_vel),
+        y_vel: settings.air_jump_y_vel.unwrap_or(y_vel),
+    }
+}
+
+/// The y velocity of each air jump from the fighters multi jump data, empty if the fighter has none
+fn multi_jump_y_vels(brawl_fighter: &BrawlFighter) -> Vec<f32> {
+    brawl_fighter.get_fighter_data()
+        .and_then(|x| x.misc.multi_jump.as_ref())
+        .map(|x| x.hops.clone())
+        .unwrap_or_default()
+}
@@ -0,0 +1,77 @@
+use brawllib_rs::fighter::Fighter as BrawlFighter;
+use brawllib_rs::high_level_fighter::HighLevelHurtBox;
+use brawllib_rs::mdl0::bones::Bone;
+use cgmath::{Deg, Euler, Matrix4, SquareMatrix};
+
+/// Finds the animated position of a bone on each frame.
+///
+/// HighLevelFighter only provides the animated transforms of bones that have a hurtbox attached.
+/// For a bone without a hurtbox we take the animated transform of the nearest ancestor that does
+/// and apply the rest pose local transforms of every bone from that ancestor down to the bone.
+pub(crate) struct BoneChain {
+    links: Vec<BoneLink>,
+}
+
+/// An ancestor of the bone, including the bone itself, and the transform from the ancestor to the bone
+struct BoneLink {
+    index:     i32,
+    transform: Matrix4<f32>,
+}
+
+impl BoneChain {
+    /// Returns None if the fighter has no bone named name
+    pub(crate) fn new(brawl_fighter: &BrawlFighter, name: &str) -> Option<BoneChain> {
+        let root = brawl_fighter.get_bones()?;
+        let mut path = vec!();
+        if !bone_path(root, name, &mut path) {
+            return None;
+        }
+
+        // links are ordered from the bone up to the root, closest first
+        let mut links = vec!();
+        let mut transform = Matrix4::identity();
+        for bone in path.iter().rev() {
+            links.push(BoneLink { index: bone.index, transform });
+            transform = local_transform(bone) * transform;
+        }
+        Some(BoneChain { links })
+    }
+
+    /// The position of the bone on the plane of play for the frame with the given hurtboxes
+    pub(crate) fn position(&self, hurt_boxes: &[HighLevelHurtBox]) -> Option<(f32, f32)> {
+        for link in &self.links {
+            if let Some(hurt_box) = hurt_boxes.iter().find(|x| x.hurt_box.bone_index as i32 == link.index) {
+                let transform = hurt_box.bone_matrix * link.transform;
+                // same x, y plane as the hurtbox colboxes
+                let position = (transform.w.z, transform.w.y);
+                if position.0.is_finite() && position.1.is_finite() {
+                    return Some(position);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// The rest pose transform of bone relative to its parent
+fn local_transform(bone: &Bone) -> Matrix4<f32> {
+    let scale = Matrix4::from_nonuniform_scale(bone.scale.x, bone.scale.y, bone.scale.z);
+    let rot = Matrix4::from(Euler::new(Deg(bone.rot.x), Deg(bone.rot.y), Deg(bone.rot.z)));
+    let translate = Matrix4::from_translation(bone.translate);
+    translate * rot * scale
+}
+
+/// Fills path with the bones from bone to the bone named name, returns false if there is no such bone
+fn bone_path<'a>(bone: &'a Bone, name: &str, path: &mut Vec<&'a Bone>) -> bool {
+    path.push(bone);
+    if bone.name == name {
+        return true;
+    }
+    for child in &bone.children {
+        if bone_path(child, name, path) {
+            return true;
+        }
+    }
+    path.pop();
+    false
+}
@@ -1,34 +1,242 @@
 use std::env;
+use std::path::PathBuf;
 
-use getopts::Options;
+use getopts::{Options, Matches};
 
-fn print_usage(program: &str, opts: Options) {
-    let brief = format!("Usage: {} [options] [list of fighters to export]", program);
+use crate::profile::Profile;
+
+const COMMANDS: &str = "Commands:
+    export    export fighters to a PF Sandbox package, used when no command is given
+    list      list fighters found in the brawl and mod files
+    inspect   display the brawl data of a single fighter
+    report    convert fighters without saving and report on the conversion
+    validate  convert fighters without saving and check the result is usable by PF Sandbox
+    diff      compare the converted fighters of two mod stacks
+
+Run `COMMAND --help` for the options of each command";
+
+fn print_usage(program: &str) {
+    println!("Usage: {} COMMAND [options]\n\n{}", program, COMMANDS);
+}
+
+fn print_command_usage(program: &str, command: &str, free: &str, opts: &Options) {
+    let brief = format!("Usage: {} {} [options] {}", program, command, free);
     print!("{}", opts.usage(&brief));
 }
 
-pub(crate) fn parse_cli() -> Option<CLIResults> {
+pub(crate) fn parse_cli() -> Option<Command> {
     let args: Vec<String> = env::args().collect();
     let program = &args[0];
 
+    let (command, command_args) = match args.get(1).map(|x| x.as_str()) {
+        Some("export")   => ("export",   &args[2..]),
+        Some("list")     => ("list",     &args[2..]),
+        Some("inspect")  => ("inspect",  &args[2..]),
+        Some("report")   => ("report",   &args[2..]),
+        Some("validate") => ("validate", &args[2..]),
+        Some("diff")     => ("diff",     &args[2..]),
+        Some("-h") | Some("--help") => {
+            print_usage(program);
+            return Some(Command::Help);
+        }
+        // Fallback to export so that `brawl_exporter all` keeps working
+        Some(_) => ("export", &args[1..]),
+        None => {
+            print_usage(program);
+            return None;
+        }
+    };
+
     let mut opts = Options::new();
-    opts.optopt("m", "mod", "name of mod folder in data/ that should be included over brawl", "FOLDER_NAME");
+    opts.optflag("h", "help", "display this help");
+    data_opts(&mut opts);
+    match command {
+        "export" => {
+            filter_opts(&mut opts);
+            settings_opt(&mut opts);
+            opts.optopt("o", "out", "directory the package is written to, defaults to PF Sandbox's package directory", "DIR");
+            jobs_opt(&mut opts);
+        }
+        "list" => {
+            filter_opts(&mut opts);
+            jobs_opt(&mut opts);
+        }
+        "report" | "validate" => {
+            filter_opts(&mut opts);
+            settings_opt(&mut opts);
+            jobs_opt(&mut opts);
+        }
+        "diff" => {
+            filter_opts(&mut opts);
+            settings_opt(&mut opts);
+            jobs_opt(&mut opts);
+            opts.optmulti("", "other-mod", "name of mod folder in the mods directory to compare against, can be given multiple times to layer mods", "FOLDER_NAME");
+        }
+        _ => { }
+    }
+    let free = match command {
+        "inspect" => "FIGHTER",
+        _         => "[list of fighter name patterns]",
+    };
 
-    let matches = match opts.parse(&args[1..]) {
+    let matches = match opts.parse(command_args) {
         Ok(m) => m,
-        Err(_) => {
-            print_usage(program, opts);
+        Err(err) => {
+            println!("{}\n", err);
+            print_command_usage(program, command, free, &opts);
             return None;
         }
     };
 
-    Some(CLIResults {
-        mod_name:        matches.opt_str("m"),
-        export_fighters: matches.free.iter().map(|x| x.to_lowercase()).collect()
+    if matches.opt_present("h") {
+        print_command_usage(program, command, free, &opts);
+        return Some(Command::Help);
+    }
+
+    let data = data_args(&matches);
+    Some(match command {
+        "export" => Command::Export {
+            data,
+            filter:   filter_args(&matches),
+            settings: matches.opt_str("s").map(PathBuf::from),
+            profile:  profile_arg(&matches)?,
+            out_dir:  matches.opt_str("o").map(PathBuf::from),
+            jobs:     jobs_arg(&matches)?,
+        },
+        // These commands look at every fighter as `all` would select them unless patterns are given
+        "list" => Command::List {
+            data,
+            filter: filter_args_or_all(&matches),
+            jobs:   jobs_arg(&matches)?,
+        },
+        "report" => Command::Report {
+            data,
+            filter:   filter_args_or_all(&matches),
+            settings: matches.opt_str("s").map(PathBuf::from),
+            profile:  profile_arg(&matches)?,
+            jobs:     jobs_arg(&matches)?,
+        },
+        "validate" => Command::Validate {
+            data,
+            filter:   filter_args_or_all(&matches),
+            settings: matches.opt_str("s").map(PathBuf::from),
+            profile:  profile_arg(&matches)?,
+            jobs:     jobs_arg(&matches)?,
+        },
+        "diff" => Command::Diff {
+            data,
+            other_mod_names: matches.opt_strs("other-mod"),
+            filter:          filter_args_or_all(&matches),
+            settings:        matches.opt_str("s").map(PathBuf::from),
+            profile:         profile_arg(&matches)?,
+            jobs:            jobs_arg(&matches)?,
+        },
+        "inspect" => {
+            if matches.free.len() != 1 {
+                println!("Expected a single fighter name\n");
+                print_command_usage(program, command, free, &opts);
+                return None;
+            }
+            Command::Inspect {
+                data,
+                fighter: matches.free[0].to_lowercase(),
+            }
+        }
+        _ => unreachable!(),
     })
 }
 
-pub struct CLIResults {
-    pub mod_name:        Option<String>,
-    pub export_fighters: Vec<String>,
+fn data_opts(opts: &mut Options) {
+    opts.optmulti("m", "mod", "name of mod folder in the mods directory that should be included over brawl, can be given multiple times with later mods included over earlier mods", "FOLDER_NAME");
+    opts.optopt("b", "brawl-dir", "directory containing the extracted brawl files, defaults to data/Brawl", "DIR");
+    opts.optopt("d", "mods-dir", "directory containing mod folders, defaults to data", "DIR");
+}
+
+fn filter_opts(opts: &mut Options) {
+    opts.optmulti("e", "exclude", "fighter name pattern to exclude, can be given multiple times", "PATTERN");
+    opts.optflag("u", "include-unmodified", "include fighters that a mod does not modify");
+}
+
+fn settings_opt(opts: &mut Options) {
+    opts.optopt("s", "settings", "toml file overriding the default values used for data brawl does not provide", "FILE");
+    opts.optopt("p", "profile", "game mechanics given to every fighter: brawl, pm, custom (from the settings file) or auto (pm for psa modded fighters, otherwise brawl), defaults to auto", "PROFILE");
+}
+
+fn jobs_opt(opts: &mut Options) {
+    opts.optopt("j", "jobs", "number of fighters to process concurrently, defaults to the number of cpus", "NUM");
+}
+
+fn data_args(matches: &Matches) -> DataArgs {
+    DataArgs {
+        mod_names: matches.opt_strs("m"),
+        brawl_dir: matches.opt_str("b").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("data/Brawl")),
+        mods_dir:  matches.opt_str("d").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("data")),
+    }
+}
+
+fn filter_args(matches: &Matches) -> FilterArgs {
+    FilterArgs {
+        include:            matches.free.iter().map(|x| x.to_lowercase()).collect(),
+        exclude:            matches.opt_strs("e"),
+        include_unmodified: matches.opt_present("u"),
+    }
+}
+
+fn filter_args_or_all(matches: &Matches) -> FilterArgs {
+    let mut filter = filter_args(matches);
+    if filter.include.is_empty() {
+        filter.include.push(String::from("all"));
+    }
+    filter
+}
+
+/// Returns None if the argument is invalid
+fn profile_arg(matches: &Matches) -> Option<Profile> {
+    match matches.opt_str("p") {
+        Some(name) => {
+            let profile = Profile::from_name(&name);
+            if profile.is_none() {
+                println!("--profile must be one of brawl, pm, custom or auto");
+            }
+            profile
+        }
+        None => Some(Profile::Auto)
+    }
+}
+
+/// Returns None if the argument is invalid
+fn jobs_arg(matches: &Matches) -> Option<Option<usize>> {
+    match matches.opt_str("j").map(|x| x.parse::<usize>()) {
+        Some(Ok(jobs)) if jobs > 0 => Some(Some(jobs)),
+        Some(_) => {
+            println!("--jobs must be a number greater than 0");
+            None
+        }
+        None => Some(None)
+    }
+}
+
+/// Where the brawl and mod files are read from
+pub struct DataArgs {
+    pub mod_names: Vec<String>,
+    pub brawl_dir: PathBuf,
+    pub mods_dir:  PathBuf,
+}
+
+/// Which fighters are selected
+pub struct FilterArgs {
+    pub include:            Vec<String>,
+    pub exclude:            Vec<String>,
+    pub include_unmodified: bool,
+}
+
+pub enum Command {
+    Export   { data: DataArgs, filter: FilterArgs, settings: Option<PathBuf>, profile: Profile, out_dir: Option<PathBuf>, jobs: Option<usize> },
+    List     { data: DataArgs, filter: FilterArgs, jobs: Option<usize> },
+    Inspect  { data: DataArgs, fighter: String },
+    Report   { data: DataArgs, filter: FilterArgs, settings: Option<PathBuf>, profile: Profile, jobs: Option<usize> },
+    Validate { data: DataArgs, filter: FilterArgs, settings: Option<PathBuf>, profile: Profile, jobs: Option<usize> },
+    Diff     { data: DataArgs, other_mod_names: Vec<String>, filter: FilterArgs, settings: Option<PathBuf>, profile: Profile, jobs: Option<usize> },
+    /// Help was displayed, there is nothing else to do
+    Help,
 }
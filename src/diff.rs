@@ -0,0 +1,83 @@
+use serde_json::Value;
+
+use crate::cli::DataArgs;
+use crate::export::{self, ExportError};
+use crate::filter::FighterFilter;
+use crate::layers;
+use crate::profile::Profile;
+use crate::settings::Settings;
+
+/// Differences displayed per fighter before the rest are summarized
+const MAX_DIFFERENCES: usize = 50;
+
+/// Convert all fighters selected by filter from both data and other_data and display the differences between them
+pub(crate) fn diff(data: &DataArgs, other_data: &DataArgs, settings: &Settings, profile: &Profile, jobs: Option<usize>, filter: &FighterFilter) -> Result<(), ExportError> {
+    let mut conversion = export::convert(data, settings, profile, jobs, filter)?;
+    conversion.require_fighters()?;
+    let mut other_conversion = export::convert(other_data, settings, profile, jobs, filter)?;
+    other_conversion.require_fighters()?;
+
+    for converted in &conversion.fighters {
+        let name = &converted.name;
+        match other_conversion.fighters.iter().find(|x| x.name == *name) {
+            Some(other_converted) => {
+                let value = serde_json::to_value(&converted.fighter).unwrap();
+                let other_value = serde_json::to_value(&other_converted.fighter).unwrap();
+
+                let mut differences = vec!();
+                diff_value(String::new(), &value, &other_value, &mut differences);
+                if !differences.is_empty() {
+                    println!("{}: {} differences", name, differences.len());
+                    for difference in differences.iter().take(MAX_DIFFERENCES) {
+                        println!("    {}", difference);
+                    }
+                    if differences.len() > MAX_DIFFERENCES {
+                        println!("    ... and {} more", differences.len() - MAX_DIFFERENCES);
+                    }
+                }
+            }
+            None => println!("{}: only in {}", name, layers::title(&data.mod_names)),
+        }
+    }
+
+    for other_converted in &other_conversion.fighters {
+        if !conversion.fighters.iter().any(|x| x.name == other_converted.name) {
+            println!("{}: only in {}", other_converted.name, layers::title(&other_data.mod_names));
+        }
+    }
+
+    Ok(())
+}
+
+/// Records every leaf of the json trees that differ
+fn diff_value(path: String, value: &Value, other_value: &Value, differences: &mut Vec<String>) {
+    match (value, other_value) {
+        (Value::Object (map), Value::Object (other_map)) => {
+            for (key, child) in map {
+                let child_path = format!("{}.{}", path, key);
+                match other_map.get(key) {
+                    Some(other_child) => diff_value(child_path, child, other_child, differences),
+                    None              => differences.push(format!("{}: {} -> missing", child_path, child)),
+                }
+            }
+            for (key, other_child) in other_map {
+                if !map.contains_key(key) {
+                    differences.push(format!("{}.{}: missing -> {}", path, key, other_child));
+                }
+            }
+        }
+        (Value::Array (array), Value::Array (other_array)) => {
+            for (i, (child, other_child)) in array.iter().zip(other_array.iter()).enumerate() {
+                diff_value(format!("{}[{}]", path, i), child, other_child, differences);
+            }
+            if array.len() != other_array.len() {
+                differences.push(format!("{}: length {} -> {}", path, array.len(), other_array.len()));
+            }
+        }
+        _ => {
+            if value != other_value {
+                differences.push(format!("{}: {} -> {}", path, value, other_value));
+            }
+        }
+    }
+}
@@ -1,372 +1,590 @@
-use std::path::PathBuf;
+use std::any::Any;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
 
 use pf_sandbox_lib::package::Package;
 use pf_sandbox_lib::fighter::*;
 use pf_sandbox_lib::stage::Stage;
 use treeflection::context_vec::ContextVec;
-use brawllib_rs::high_level_fighter::{HighLevelFighter, CollisionBoxValues};
-use brawllib_rs::fighter::ModType;
-use brawllib_rs::script_ast::{EdgeSlide, AngleFlip, HurtBoxState};
+use brawllib_rs::high_level_fighter::{HighLevelFighter, HighLevelFrame};
+use brawllib_rs::fighter::Fighter as BrawlFighter;
+use brawllib_rs::script_ast::EdgeSlide;
 use brawllib_rs::script_runner::VelModify as BrawlVelModify;
 use brawllib_rs::brawl_mod::BrawlMod;
 use noisy_float::prelude::*;
-
-use cgmath::Matrix4;
+use serde::Serialize;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
 
 use crate::action_map::action_name_to_indexes;
+use crate::air_jump;
+use crate::cli::DataArgs;
+use crate::filter::{FighterFilter, Selection};
+use crate::hitbox;
+use crate::hold::HoldBones;
+use crate::hurtbox;
+use crate::layers::{self, ModLayers};
+use crate::profile::{Profile, Mechanics};
+use crate::settings::{Settings, FighterSettings};
+use crate::shield;
+use crate::summary::{ExportSummary, FighterStatus};
+use crate::warnings::Warnings;
+
+pub(crate) enum ExportError {
+    /// The brawl files or mod files could not be loaded
+    Load (String),
+    /// None of the loaded fighters were selected by the fighter filter or matched the requested name
+    NoFightersMatched,
+    /// The package could not be written to --out
+    Save { path: PathBuf, error: io::Error },
+    /// The package could not be written to PF Sandbox's package directory
+    SavePackage (String),
+    /// A fighter contained data that cannot be converted to a PF Sandbox fighter
+    InvalidFighterData { fighter: String, reason: String },
+    /// The converted fighters contained this many problems
+    ValidationFailed (usize),
+    /// The settings file could not be loaded
+    Settings (String),
+}
 
-/// Export all fighters listed in export_fighters
-/// if mod_path is Some then mod files overwrite vanilla files
-/// Not always correct, but assumes that psa modded characters are from PM
-pub(crate) fn export(mod_dir: Option<String>, export_fighters: &[String]) {
-    let mod_path = if let &Some(ref mod_dir) = &mod_dir {
-        Some(PathBuf::from("data").join(mod_dir))
-    } else {
-        None
-    };
-    let brawl_path = PathBuf::from("data/Brawl");
-    let brawl_mod = BrawlMod::new(&brawl_path, mod_path.as_ref().map(|x| x.as_path()));
-
-    let brawl_fighters = match brawl_mod.load_fighters(true) {
-        Ok(fighters) => fighters,
-        Err(err) => {
-            println!("Failed to load brawl mod: {}", err);
-            return;
+impl ExportError {
+    /// The process exit code used to report this error
+    pub(crate) fn exit_code(&self) -> i32 {
+        match self {
+            ExportError::Load (_)                  => 2,
+            ExportError::NoFightersMatched         => 3,
+            ExportError::Save { .. }               => 4,
+            ExportError::SavePackage (_)           => 4,
+            ExportError::InvalidFighterData { .. } => 5,
+            ExportError::ValidationFailed (_)      => 6,
+            ExportError::Settings (_)              => 7,
         }
-    };
+    }
+}
 
-    let mut package = if let Some(name) = mod_dir.clone() {
-        Package::blank(&name)
-    } else {
-        Package::blank("brawl")
-    };
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExportError::Load (err) => write!(f, "Failed to load brawl mod: {}", err),
+            ExportError::NoFightersMatched => write!(f, "No fighters matched the requested fighter names"),
+            ExportError::Save { path, error } => write!(f, "Failed to save package to {}: {}", path.to_string_lossy(), error),
+            ExportError::SavePackage (err) => write!(f, "Failed to save package to PF Sandbox's package directory: {}", err),
+            ExportError::InvalidFighterData { fighter, reason } => write!(f, "Fighter {} contains invalid data: {}", fighter, reason),
+            ExportError::ValidationFailed (count) => write!(f, "Validation found {} problems", count),
+            ExportError::Settings (err) => write!(f, "Failed to load settings file {}", err),
+        }
+    }
+}
+
+/// Export all fighters selected by filter
+/// files in each of the data.mods_dir/data.mod_names overwrite vanilla files in data.brawl_dir, later mods overwrite earlier mods
+/// if out_path is Some the package is written there instead of PF Sandbox's package directory
+/// jobs is the number of fighters converted concurrently, None uses the number of cpus
+/// profile selects the game mechanics given to each fighter
+pub(crate) fn export(data: &DataArgs, settings: &Settings, profile: &Profile, out_path: Option<&Path>, jobs: Option<usize>, filter: &FighterFilter) -> Result<(), ExportError> {
+    let mut conversion = convert(data, settings, profile, jobs, filter)?;
+    conversion.summary.print();
+    conversion.require_fighters()?;
+
+    let mut package = Package::blank(&layers::package_name(&data.mod_names));
     package.fighters.clear();
     package.stages.push(String::from("Stage"), Stage::default());
+    for converted in conversion.fighters {
+        package.fighters.push(converted.name, converted.fighter);
+    }
 
-    for brawl_fighter in brawl_fighters {
-        // Filter unmodified fighters from mods, so that deleted fighters from mods don't show up as brawl fighters
-        let unmodified_fighter_in_mod = match brawl_fighter.mod_type {
-            ModType::NotMod         => true,
-            ModType::ModFromBase    => false,
-            ModType::ModFromScratch => false,
-        } && mod_path.is_some();
-
-        let lower_fighter_name = brawl_fighter.cased_name.to_lowercase();
-        if export_fighters.contains(&lower_fighter_name) || export_fighters.contains(&String::from("all")) && lower_fighter_name != "poketrainer" && !unmodified_fighter_in_mod {
-            let hl_fighter = HighLevelFighter::new(&brawl_fighter);
-            info!("starting export fighter: {}", brawl_fighter.cased_name);
-            let mut fighter = Fighter::default();
-            fighter.name = hl_fighter.name.clone();
-
-            let attributes = hl_fighter.attributes;
-            fighter.air_jumps = attributes.num_jumps as u64 - 1;
-            fighter.weight = attributes.weight / 100.0;
-            fighter.gravity = -attributes.gravity;
-            fighter.terminal_vel = -attributes.term_vel;
-            fighter.fastfall_terminal_vel = -attributes.fastfall_velocity;
-            fighter.jump_y_init_vel = attributes.jump_y_init_vel;
-            fighter.jump_y_init_vel_short = attributes.jump_y_init_vel_short;
-            fighter.jump_x_init_vel = attributes.jump_x_init_vel;
-            fighter.jump_x_term_vel = attributes.jump_x_init_term_vel;
-            fighter.jump_x_vel_ground_mult = attributes.jump_x_vel_ground_mult;
-            fighter.air_mobility_a = attributes.air_mobility_a;
-            fighter.air_mobility_b = attributes.air_mobility_b;
-            fighter.air_x_term_vel = attributes.air_x_term_vel;
-            fighter.air_friction = attributes.air_friction_x;
-            fighter.air_jump_x_vel = 1.0; // attributes.air_jump_x_mult; // TODO: store air_jump in an enum to allow both brawl and melee physics
-            fighter.air_jump_y_vel = 2.0; // attributes.air_jump_y_mult;
-            fighter.walk_init_vel = attributes.walk_init_vel;
-            fighter.walk_acc = attributes.walk_acc;
-            fighter.walk_max_vel = attributes.walk_max_vel;
-            fighter.slow_walk_max_vel = 0.0_f32.max(attributes.walk_max_vel - 0.5);
-            fighter.dash_init_vel = attributes.dash_init_vel;
-            fighter.dash_run_acc_a = attributes.dash_run_acc_a;
-            fighter.dash_run_acc_b = attributes.dash_run_acc_b;
-            fighter.dash_run_term_vel = attributes.dash_run_term_vel;
-            fighter.friction = attributes.ground_friction;
-            // fighter.aerialdodge_mult = // TODO: PM custom?
-            // fighter.aerialdodge_drift_frame = attributes. // TODO: PM custom?
-            fighter.forward_roll = true;
-            fighter.backward_roll = true;
-            fighter.spot_dodge = true;
-            fighter.aerialdodge_mult = if brawl_fighter.modded_by_psa { 3.0 } else { 0.0 };
-            fighter.lcancel = if brawl_fighter.modded_by_psa {
-                Some(LCancel {
-                    active_window: 7,
-                    frame_skip: 1,
-                    normal_land: false,
-                })
-            } else {
-                None
-            };
-            fighter.shield = Some(Shield {
-                // TODO: shield_strength !??!?!
-                stick_lock: false,
-                stick_mult: 3.0,
-                offset_x:   1.0,
-                offset_y:   8.0,
-                break_vel:  attributes.shield_break_vel,
-                scaling:    attributes.shield_size,
-                hp_scaling: 1.15,
-                hp_max:     60.0,
-                hp_regen:   0.1,
-                hp_cost:    0.28,
-            });
-            fighter.power_shield = Some(PowerShield {
-                reflect_window: Some(2),
-                parry: Some(PowerShieldEffect {
-                    window: 4,
-                    duration: 4,
-                }),
-                enemy_stun: None,
-            });
-            fighter.tech = Some(Tech::default());
-            fighter.missed_tech_forced_getup = Some(220); // TODO
-            fighter.run_turn_flip_dir_frame = attributes.flip_dir_frame as u64; // TODO
-            fighter.tilt_turn_flip_dir_frame = attributes.flip_dir_frame as u64;
-            fighter.tilt_turn_into_dash_iasa = attributes.flip_dir_frame as u64;
-
-            // create fighter actions
-            // The PF Sandbox action is equivalent to the Brawl subaction
-            for hl_subaction in hl_fighter.subactions {
-                let mut frames = ContextVec::new();
-                let mut initial_hit = true;
-                for hl_frame in hl_subaction.frames {
-                    // https://smashboards.com/threads/all-aboard-the-pain-train-hurtboxes.301220/
-                    // Hurtboxes like hitboxes have a reference to a single bone that determines its position + an offset vector.
-                    // However hurtboxes have radius and stretch values that give them the (usually) cylindrical shape.
-                    // We create two linked colboxes for each hurtbox, this is not accurate but is the best we can do.
-                    let mut colboxes = vec!();
-                    let mut colbox_links = vec!();
-                    let mut render_order = vec!();
-
-                    for hurt_box in hl_frame.hurt_boxes {
-                        let transform = hurt_box.bone_matrix * Matrix4::<f32>::from_translation(hurt_box.hurt_box.offset);
-                        let role = match hurt_box.state {
-                            HurtBoxState::Unknown(_) |
-                            HurtBoxState::Normal => CollisionBoxRole::Hurt(HurtBox::default()),
-                            HurtBoxState::Invincible => CollisionBoxRole::Invincible,
-                            HurtBoxState::IntangibleFlashing |
-                            HurtBoxState::IntangibleNoFlashing |
-                            HurtBoxState::IntangibleQuickFlashing => CollisionBoxRole::Intangible,
-                        };
-
-                        colboxes.push(CollisionBox {
-                            point: (transform.w.z, transform.w.y),
-                            radius: hurt_box.hurt_box.radius, // TODO: radius is not accurate, needs to take Z offset into account; however it certainly looks fine, so eh
-                            role: role.clone(),
-                        });
-
-                        // TODO: Works well when there are two stretch offsets, if there are three then it will likely be wonky.
-                        //       Maybe use a heuristic for using a single colbox with custom radius if there is a large 3rd offset.
-                        //       If this isnt actually an issue just delete comment
-                        let s = hurt_box.hurt_box.stretch;
-                        let tuple = if s.x != 0.0 || s.y != 0.0 || s.z != 0.0 { // If there are no stretch offsets we only need one colbox
-                            let stretch_transform = transform * Matrix4::<f32>::from_translation(s);
-                            colboxes.push(CollisionBox {
-                                point: (stretch_transform.w.z, stretch_transform.w.y),
-                                radius: hurt_box.hurt_box.radius,
-                                role: role,
-                            });
-
-                            colbox_links.push(CollisionBoxLink {
-                                one: colboxes.len() - 2,
-                                two: colboxes.len() - 1,
-                                link_type: LinkType::MeldFirst,
-                            });
-
-                            (
-                                RenderOrder::Link(colbox_links.len() - 1),
-                                (transform.w.x + stretch_transform.w.x) / 2.0, // average of the z values for both colboxes
-                            )
-                        } else {
-                            (
-                                RenderOrder::Colbox(colboxes.len() - 1),
-                                transform.w.x,
-                            )
-                        };
-
-                        if tuple.1.is_nan() {
-                            error!("Skipped render_order element, value was NaN");
-                        }
-                        else {
-                            render_order.push(tuple);
-                        }
-                    }
+    package.meta.title = layers::title(&data.mod_names);
+    if let Some(out_path) = out_path {
+        save_package_to(&package, out_path).map_err(|error| ExportError::Save { path: out_path.to_path_buf(), error })?;
+    } else {
+        // Package::save does not return write errors, it panics on them
+        panic::catch_unwind(AssertUnwindSafe(|| package.save()))
+            .map_err(|payload| ExportError::SavePackage (panic_message(&payload)))?;
+    }
 
-                    for hit_box in hl_frame.hit_boxes {
-                        let role = match hit_box.next_values {
-                            CollisionBoxValues::Hit(values) => {
-                                if !values.enabled || !values.can_hit_fighter() {
-                                    continue;
-                                }
-
-                                let enable_reverse_hit = if let AngleFlip::AwayFromAttacker = values.angle_flipping { true } else { false };
-                                let angle = if let AngleFlip::AttackerDirReverse = values.angle_flipping { 180 - values.trajectory } else { values.trajectory } as f32;
-                                CollisionBoxRole::Hit (HitBox {
-                                    shield_damage:      values.shield_damage as f32,
-                                    damage:             values.damage as f32,
-                                    bkb:                values.bkb as f32,
-                                    kbg:                values.kbg as f32 / 100.0,
-                                    hitstun:            HitStun::FramesTimesKnockback(0.4),
-                                    enable_clang:       values.clang,
-                                    enable_rebound:     values.clang, // TODO: are these the same thing?
-                                    effect:             HitboxEffect::None,
-                                    enable_reverse_hit,
-                                    angle,
-                                })
-                            }
-                            CollisionBoxValues::Grab (_) => {
-                                CollisionBoxRole::Grab
-                            }
-                        };
-
-                        colboxes.push(CollisionBox {
-                            point: (hit_box.next_pos.z, hit_box.next_pos.y),
-                            radius: hit_box.next_size,
-                            role: role.clone()
-                        });
-
-                        if let Some(prev_pos) = hit_box.prev_pos {
-                            colboxes.push(CollisionBox {
-                                point: (prev_pos.z, prev_pos.y),
-                                radius: hit_box.prev_size.unwrap(),
-                                role // TODO: is role always the same? if so remove prev_values from HighLevelHitBox
-                            });
-
-                            colbox_links.push(CollisionBoxLink {
-                                one: colboxes.len() - 2,
-                                two: colboxes.len() - 1,
-                                link_type: LinkType::MeldFirst,
-                            });
-
-                            render_order.push((
-                                RenderOrder::Link(colbox_links.len() - 1),
-                                -99999.0
-                            ));
-                        }
-                        else {
-                            render_order.push((
-                                RenderOrder::Colbox(colboxes.len() - 1),
-                                -99999.0
-                            ));
-                        }
-                    }
+    // The fighters that did convert are still saved, but a partial export is reported as a failure
+    match conversion.first_failure {
+        Some(err) => Err(err),
+        None      => Ok(()),
+    }
+}
 
-                    render_order.sort_by_key(|x| n32(x.1));
+pub(crate) struct ConvertedFighter {
+    pub name:     String,
+    pub fighter:  Fighter,
+    pub warnings: Warnings,
+}
 
-                    let ledge_cancel = match hl_frame.edge_slide {
-                        | EdgeSlide::Airbourne
-                        | EdgeSlide::SlideOff    => true,
-                        | EdgeSlide::StayOn
-                        | EdgeSlide::Unknown (_) => false
-                    };
+/// The result of converting every fighter selected by a filter
+pub(crate) struct Conversion {
+    /// Successfully converted fighters, in the order they were loaded
+    pub fighters:  Vec<ConvertedFighter>,
+    pub summary:   ExportSummary,
+    first_failure: Option<ExportError>,
+}
 
-                    let ecb = ECB {
-                        left:   hl_frame.ecb.left,
-                        right:  hl_frame.ecb.right,
-                        top:    hl_frame.ecb.top,
-                        bottom: hl_frame.ecb.bottom,
-                    };
+impl Conversion {
+    /// Errors if no fighters were converted, using the first failure as the error if there was one
+    pub(crate) fn require_fighters(&mut self) -> Result<(), ExportError> {
+        if self.fighters.is_empty() {
+            Err(self.first_failure.take().unwrap_or(ExportError::NoFightersMatched))
+        } else {
+            Ok(())
+        }
+    }
+}
 
-                    let pass_through = match hl_subaction.name.as_ref() {
-                        "DamageN1" |
-                        "DamageN2" |
-                        "DamageN3" |
-                        "DamageHi1" |
-                        "DamageHi2" |
-                        "DamageHi3" |
-                        "DamageLw1" |
-                        "DamageLw2" |
-                        "DamageLw3" |
-                        "DamageElec" |
-                        "DamageAir1" |
-                        "DamageAir2" |
-                        "DamageAir3" |
-                        "DamageFlyN" |
-                        "DamageFlyHi" |
-                        "DamageFlyLw" |
-                        "DamageFlyTop" |
-                        "DamageFlyRoll" |
-                        "AttackAirB" |
-                        "AttackAirF" |
-                        "AttackAirN" |
-                        "AttackAirHi" |
-                        "AttackAirLw" |
-                        "EscapeAir"
-                          => false,
-                        _ => true
-                    };
+/// Convert all fighters selected by filter without saving them
+pub(crate) fn convert(data: &DataArgs, settings: &Settings, profile: &Profile, jobs: Option<usize>, filter: &FighterFilter) -> Result<Conversion, ExportError> {
+    let has_mod = !data.mod_names.is_empty();
+    let brawl_fighters = load_fighters(data)?;
+
+    let selections: Vec<Selection> = brawl_fighters.iter().map(|x| filter.select(x, has_mod)).collect();
+
+    let results = map_fighters(&brawl_fighters, jobs, |i, brawl_fighter| {
+        if let Selection::Export = selections[i] {
+            let mechanics = profile.mechanics(brawl_fighter, settings);
+            let mut warnings = Warnings::new();
+            export_fighter(brawl_fighter, settings.fighter(&brawl_fighter.cased_name), &mechanics, &mut warnings)
+                .map(|fighter| Some((fighter, warnings)))
+        } else {
+            Ok(None)
+        }
+    });
 
-                    let x_vel_modify = match hl_frame.x_vel_modify {
-                        BrawlVelModify::Set (vel) => VelModify::Set (vel),
-                        BrawlVelModify::Add (vel) => VelModify::Add (vel),
-                        BrawlVelModify::None      => VelModify::None,
-                    };
-                    let y_vel_modify = match hl_frame.y_vel_modify {
-                        BrawlVelModify::Set (vel) => VelModify::Set (vel),
-                        BrawlVelModify::Add (vel) => VelModify::Add (vel),
-                        BrawlVelModify::None      => VelModify::None,
-                    };
+    let mut fighters = vec!();
+    let mut summary = ExportSummary::new();
+    for name in filter.unmatched(&brawl_fighters) {
+        summary.push_unmatched(name);
+    }
+    let mut first_failure = None;
+    for ((brawl_fighter, selection), result) in brawl_fighters.iter().zip(selections.iter()).zip(results) {
+        match result {
+            Ok(Some((fighter, warnings))) => {
+                summary.push(&brawl_fighter.cased_name, FighterStatus::Exported (warnings.len()));
+                fighters.push(ConvertedFighter { name: brawl_fighter.cased_name.clone(), fighter, warnings });
+            }
+            Err(err) => {
+                error!("{}", err);
+                summary.push(&brawl_fighter.cased_name, FighterStatus::Failed (err.to_string()));
+                if first_failure.is_none() {
+                    first_failure = Some(err);
+                }
+            }
+            Ok(None) => {
+                if let Selection::Skip (reason) = selection {
+                    summary.push(&brawl_fighter.cased_name, FighterStatus::Skipped (reason.clone()));
+                }
+            }
+        }
+    }
+
+    Ok(Conversion { fighters, summary, first_failure })
+}
 
-                    // TODO: Naively applying the animation velocity to the run subaction goes way to fast.
-                    // This is a quick fix until I figure out whats going on.
-                    let x_vel_temp = if hl_subaction.name == "Run" { 0.0 } else { hl_frame.x_vel_temp };
-                    let y_vel_temp = if hl_subaction.name == "Run" { 0.0 } else { hl_frame.y_vel_temp };
+/// Run f on every fighter using a thread pool of jobs threads, None uses the number of cpus.
+/// Fighters are processed concurrently, but the results are returned in the same order as brawl_fighters
+/// so that the output is identical regardless of thread scheduling.
+/// Each fighter is isolated so that a panic from malformed data only loses the result of that fighter.
+pub(crate) fn map_fighters<T, F>(brawl_fighters: &[BrawlFighter], jobs: Option<usize>, f: F) -> Vec<Result<T, ExportError>>
+    where T: Send, F: Fn(usize, &BrawlFighter) -> Result<T, ExportError> + Sync
+{
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(jobs.unwrap_or(0))
+        .build()
+        .expect("Failed to create the fighter conversion thread pool");
+    pool.install(|| {
+        brawl_fighters.par_iter().enumerate().map(|(i, brawl_fighter)| {
+            panic::catch_unwind(AssertUnwindSafe(|| f(i, brawl_fighter)))
+                .unwrap_or_else(|payload| Err(ExportError::InvalidFighterData {
+                    fighter: brawl_fighter.cased_name.clone(),
+                    reason:  format!("panicked during conversion: {}", panic_message(&payload)),
+                }))
+        }).collect()
+    })
+}
 
-                    // Need to skip the first hit
-                    let any_rehit = hl_frame.hitbox_sets_rehit.iter().any(|x| *x);
-                    let force_hitlist_reset = any_rehit && !initial_hit;
-                    if any_rehit {
-                        initial_hit = false;
-                    }
+/// Load all fighters from data.brawl_dir with the files from each of the mods layered over them
+pub(crate) fn load_fighters(data: &DataArgs) -> Result<Vec<BrawlFighter>, ExportError> {
+    let mod_layers = ModLayers::new(&data.mods_dir, &data.mod_names).map_err(|err| ExportError::Load (err.to_string()))?;
+    let brawl_mod = BrawlMod::new(&data.brawl_dir, mod_layers.path());
+    brawl_mod.load_fighters(true).map_err(|err| ExportError::Load (err.to_string()))
+}
 
-                    let ledge_grab_box = hl_frame.ledge_grab_box.map(|ledge_grab| {
-                        LedgeGrabBox {
-                            x1: ledge_grab.left,
-                            y1: ledge_grab.up,
-                            x2: ledge_grab.right,
-                            y2: ledge_grab.down
-                        }
-                    });
+/// Convert a single brawl fighter into a PF Sandbox fighter
+/// Any data that is lost or approximated is recorded in warnings
+fn export_fighter(brawl_fighter: &BrawlFighter, settings: &FighterSettings, mechanics: &Mechanics, warnings: &mut Warnings) -> Result<Fighter, ExportError> {
+    let hl_fighter = HighLevelFighter::new(brawl_fighter);
+    info!("starting export fighter: {}", brawl_fighter.cased_name);
+    let mut fighter = Fighter::default();
+    fighter.name = hl_fighter.name.clone();
+
+    let attributes = &hl_fighter.attributes;
+    if attributes.num_jumps < 1 {
+        return Err(ExportError::InvalidFighterData {
+            fighter: brawl_fighter.cased_name.clone(),
+            reason:  format!("num_jumps is {}, a fighter needs at least one jump", attributes.num_jumps),
+        });
+    }
+    if !attributes.weight.is_finite() || attributes.weight <= 0.0 {
+        return Err(ExportError::InvalidFighterData {
+            fighter: brawl_fighter.cased_name.clone(),
+            reason:  format!("weight is {}, needs to be a positive number", attributes.weight),
+        });
+    }
+    fighter.air_jumps = attributes.num_jumps as u64 - 1;
+    fighter.weight = attributes.weight / 100.0;
+    fighter.gravity = -attributes.gravity;
+    fighter.terminal_vel = -attributes.term_vel;
+    fighter.fastfall_terminal_vel = -attributes.fastfall_velocity;
+    fighter.jump_y_init_vel = attributes.jump_y_init_vel;
+    fighter.jump_y_init_vel_short = attributes.jump_y_init_vel_short;
+    fighter.jump_x_init_vel = attributes.jump_x_init_vel;
+    fighter.jump_x_term_vel = attributes.jump_x_init_term_vel;
+    fighter.jump_x_vel_ground_mult = attributes.jump_x_vel_ground_mult;
+    fighter.air_mobility_a = attributes.air_mobility_a;
+    fighter.air_mobility_b = attributes.air_mobility_b;
+    fighter.air_x_term_vel = attributes.air_x_term_vel;
+    fighter.air_friction = attributes.air_friction_x;
+    let air_jump = air_jump::air_jump(brawl_fighter, attributes, settings, warnings);
+    fighter.air_jump_x_vel = air_jump.x_vel;
+    fighter.air_jump_y_vel = air_jump.y_vel;
+    fighter.walk_init_vel = attributes.walk_init_vel;
+    fighter.walk_acc = attributes.walk_acc;
+    fighter.walk_max_vel = attributes.walk_max_vel;
+    fighter.slow_walk_max_vel = 0.0_f32.max(attributes.walk_max_vel - 0.5);
+    fighter.dash_init_vel = attributes.dash_init_vel;
+    fighter.dash_run_acc_a = attributes.dash_run_acc_a;
+    fighter.dash_run_acc_b = attributes.dash_run_acc_b;
+    fighter.dash_run_term_vel = attributes.dash_run_term_vel;
+    fighter.friction = attributes.ground_friction;
+    // fighter.aerialdodge_drift_frame = attributes. // TODO: PM custom?
+    fighter.forward_roll = true;
+    fighter.backward_roll = true;
+    fighter.spot_dodge = true;
+    fighter.aerialdodge_mult = mechanics.aerialdodge_mult;
+    fighter.lcancel = if mechanics.lcancel_window == 0 { None } else {
+        Some(LCancel {
+            active_window: mechanics.lcancel_window,
+            frame_skip: mechanics.lcancel_frame_skip,
+            normal_land: false,
+        })
+    };
+    fighter.shield = Some(shield::shield(brawl_fighter, &hl_fighter, settings, warnings));
+    let power_shield = &mechanics.power_shield;
+    fighter.power_shield = Some(PowerShield {
+        reflect_window: if power_shield.reflect_window == 0 { None } else { Some(power_shield.reflect_window) },
+        parry: if power_shield.parry_window == 0 { None } else {
+            Some(PowerShieldEffect {
+                window: power_shield.parry_window,
+                duration: power_shield.parry_duration,
+            })
+        },
+        enemy_stun: None,
+    });
+    fighter.tech = if mechanics.tech { Some(Tech::default()) } else { None };
+    fighter.missed_tech_forced_getup = Some(settings.missed_tech_forced_getup); // TODO
+    fighter.run_turn_flip_dir_frame = attributes.flip_dir_frame as u64; // TODO
+    fighter.tilt_turn_flip_dir_frame = attributes.flip_dir_frame as u64;
+    fighter.tilt_turn_into_dash_iasa = attributes.flip_dir_frame as u64;
+
+    let hold_bones = HoldBones::new(brawl_fighter, warnings);
+    hurtbox::check_zones(&hl_fighter, warnings);
+
+    // create fighter actions
+    // The PF Sandbox action is equivalent to the Brawl subaction
+    for hl_subaction in hl_fighter.subactions {
+        let mut frames = ContextVec::new();
+        let mut initial_hits = vec!();
+        for hl_frame in hl_subaction.frames {
+            // https://smashboards.com/threads/all-aboard-the-pain-train-hurtboxes.301220/
+            // Hurtboxes like hitboxes have a reference to a single bone that determines its position + an offset vector.
+            // However hurtboxes have radius and stretch values that give them the (usually) cylindrical shape.
+            // The hurtbox module projects this shape onto the plane of play.
+            let mut colboxes = vec!();
+            let mut colbox_links = vec!();
+            let mut render_order = vec!();
+
+            let (item_hold_x, item_hold_y) = hold_bones.item_hold(&hl_frame.hurt_boxes).unwrap_or((settings.item_hold_x, settings.item_hold_y));
+            let (grab_hold_x, grab_hold_y) = hold_bones.grab_hold(&hl_frame.hurt_boxes).unwrap_or((settings.grab_hold_x, settings.grab_hold_y));
+            let movement = movement(&hl_frame);
+
+            for hurt_box in hl_frame.hurt_boxes {
+                let role = hurtbox::role(&hurt_box);
+                let (elements, depth) = hurtbox::hurt_box(&hurt_box, role, &mut colboxes, &mut colbox_links);
+                let depth = render_depth(depth, &hl_subaction.name, warnings);
+                for element in elements {
+                    render_order.push((element, depth));
+                }
+            }
 
-                    let frame = ActionFrame {
-                        ecb,
-                        colbox_links,
-                        ledge_cancel,
-                        pass_through,
-                        x_vel_modify,
-                        y_vel_modify,
-                        x_vel_temp,
-                        y_vel_temp,
-                        force_hitlist_reset,
-                        colboxes:            ContextVec::from_vec(colboxes),
-                        render_order:        render_order.iter().map(|x| x.0.clone()).collect(),
-                        ledge_grab_box:      ledge_grab_box.clone(),
-                        item_hold_x:         4.0,
-                        item_hold_y:         11.0,
-                        grab_hold_x:         4.0,
-                        grab_hold_y:         11.0,
-                        use_platform_angle:  hl_frame.slope_contour_full.is_some(),
+            for hit_box in hl_frame.hit_boxes {
+                let role = match hitbox::role(&hit_box.next_values, movement, mechanics, &hl_subaction.name, warnings) {
+                    Some(role) => role,
+                    None       => continue,
+                };
+
+                colboxes.push(CollisionBox {
+                    point: (hit_box.next_pos.z, hit_box.next_pos.y),
+                    radius: hit_box.next_size,
+                    role: role.clone()
+                });
+
+                if let Some(prev_pos) = hit_box.prev_pos {
+                    let role = match &hit_box.prev_values {
+                        Some(prev_values) => hitbox::prev_role(prev_values, &hit_box.next_values, role, movement, mechanics, &hl_subaction.name, warnings),
+                        None              => role,
                     };
+                    colboxes.push(CollisionBox {
+                        point: (prev_pos.z, prev_pos.y),
+                        radius: hit_box.prev_size.unwrap(),
+                        role
+                    });
 
-                    frames.push(frame);
+                    colbox_links.push(CollisionBoxLink {
+                        one: colboxes.len() - 2,
+                        two: colboxes.len() - 1,
+                        link_type: LinkType::MeldFirst,
+                    });
+
+                    render_order.push((
+                        RenderOrder::Link(colbox_links.len() - 1),
+                        render_depth((hit_box.next_pos.x + prev_pos.x) / 2.0, &hl_subaction.name, warnings), // average of the depths of both colboxes
+                    ));
                 }
+                else {
+                    render_order.push((
+                        RenderOrder::Colbox(colboxes.len() - 1),
+                        render_depth(hit_box.next_pos.x, &hl_subaction.name, warnings),
+                    ));
+                }
+            }
 
-                let action = ActionDef {
-                    iasa: hl_subaction.iasa as i64,
-                    frames
-                };
+            render_order.sort_by_key(|x| n32(x.1));
+
+            let ledge_cancel = match hl_frame.edge_slide {
+                | EdgeSlide::Airbourne
+                | EdgeSlide::SlideOff    => true,
+                | EdgeSlide::StayOn
+                | EdgeSlide::Unknown (_) => false
+            };
+
+            let ecb = ECB {
+                left:   hl_frame.ecb.left,
+                right:  hl_frame.ecb.right,
+                top:    hl_frame.ecb.top,
+                bottom: hl_frame.ecb.bottom,
+            };
+
+            let pass_through = match hl_subaction.name.as_ref() {
+                "DamageN1" |
+                "DamageN2" |
+                "DamageN3" |
+                "DamageHi1" |
+                "DamageHi2" |
+                "DamageHi3" |
+                "DamageLw1" |
+                "DamageLw2" |
+                "DamageLw3" |
+                "DamageElec" |
+                "DamageAir1" |
+                "DamageAir2" |
+                "DamageAir3" |
+                "DamageFlyN" |
+                "DamageFlyHi" |
+                "DamageFlyLw" |
+                "DamageFlyTop" |
+                "DamageFlyRoll" |
+                "AttackAirB" |
+                "AttackAirF" |
+                "AttackAirN" |
+                "AttackAirHi" |
+                "AttackAirLw" |
+                "EscapeAir"
+                  => false,
+                _ => true
+            };
+
+            let x_vel_modify = match hl_frame.x_vel_modify {
+                BrawlVelModify::Set (vel) => VelModify::Set (vel),
+                BrawlVelModify::Add (vel) => VelModify::Add (vel),
+                BrawlVelModify::None      => VelModify::None,
+            };
+            let y_vel_modify = match hl_frame.y_vel_modify {
+                BrawlVelModify::Set (vel) => VelModify::Set (vel),
+                BrawlVelModify::Add (vel) => VelModify::Add (vel),
+                BrawlVelModify::None      => VelModify::None,
+            };
 
-                for index in action_name_to_indexes(&hl_subaction.name) {
-                    fighter.actions[index] = action.clone();
+            // TODO: Naively applying the animation velocity to the run subaction goes way to fast.
+            // This is a quick fix until I figure out whats going on.
+            let x_vel_temp = if hl_subaction.name == "Run" { 0.0 } else { hl_frame.x_vel_temp };
+            let y_vel_temp = if hl_subaction.name == "Run" { 0.0 } else { hl_frame.y_vel_temp };
+
+            // Brawl tracks which fighters have been hit separately for each hitbox set.
+            // A set rehits on its first hit too, so the first rehit of each set needs to be skipped.
+            // PF Sandbox has a single hitlist, so it is reset when any set that has already hit rehits,
+            // which also lets sets that already hit and are not rehitting hit again.
+            let mut reset_sets = vec!();
+            let mut other_sets = vec!();
+            for (set, rehit) in hl_frame.hitbox_sets_rehit.iter().enumerate() {
+                if set >= initial_hits.len() {
+                    initial_hits.resize(set + 1, true);
                 }
+                if *rehit {
+                    if !initial_hits[set] {
+                        reset_sets.push(set);
+                    }
+                    initial_hits[set] = false;
+                }
+                else if !initial_hits[set] {
+                    other_sets.push(set);
+                }
+            }
+            let force_hitlist_reset = !reset_sets.is_empty();
+            if force_hitlist_reset && !other_sets.is_empty() {
+                warnings.subaction(&hl_subaction.name, format!("hitbox sets {:?} rehit while sets {:?} that already hit do not, PF Sandbox resets the hitlist of every set", reset_sets, other_sets));
             }
 
-            package.fighters.push(brawl_fighter.cased_name, fighter);
+            let ledge_grab_box = hl_frame.ledge_grab_box.and_then(|ledge_grab| {
+                ledge_grab_box(ledge_grab.left, ledge_grab.right, ledge_grab.up, ledge_grab.down, &hl_subaction.name, warnings)
+            });
+
+            let frame = ActionFrame {
+                ecb,
+                colbox_links,
+                ledge_cancel,
+                pass_through,
+                x_vel_modify,
+                y_vel_modify,
+                x_vel_temp,
+                y_vel_temp,
+                force_hitlist_reset,
+                item_hold_x,
+                item_hold_y,
+                grab_hold_x,
+                grab_hold_y,
+                colboxes:            ContextVec::from_vec(colboxes),
+                render_order:        render_order.iter().map(|x| x.0.clone()).collect(),
+                ledge_grab_box:      ledge_grab_box.clone(),
+                use_platform_angle:  hl_frame.slope_contour_full.is_some(),
+            };
+
+            frames.push(frame);
+        }
+
+        let action = ActionDef {
+            iasa: hl_subaction.iasa as i64,
+            frames
+        };
+
+        for index in action_name_to_indexes(&hl_subaction.name) {
+            fighter.actions[index] = action.clone();
+        }
+    }
+
+    Ok(fighter)
+}
+
+/// Brawl and PF Sandbox both define the ledge grab box relative to a fighter facing right.
+/// PF Sandbox expects x1 < x2 and y1 < y2, so the box is normalised and boxes from mods that need normalising are reported.
+/// Returns None if the box cannot be used.
+fn ledge_grab_box(left: f32, right: f32, up: f32, down: f32, subaction: &str, warnings: &mut Warnings) -> Option<LedgeGrabBox> {
+    if !(left.is_finite() && right.is_finite() && up.is_finite() && down.is_finite()) {
+        warnings.subaction(subaction, String::from("ledge grab box contains a non finite value, it is not exported"));
+        return None;
+    }
+    if left > right {
+        warnings.subaction(subaction, format!("ledge grab box left {} is right of right {}, swapped", left, right));
+    }
+    if down > up {
+        warnings.subaction(subaction, format!("ledge grab box down {} is above up {}, swapped", down, up));
+    }
+    if left == right || up == down {
+        warnings.subaction(subaction, String::from("ledge grab box has no area, it is not exported"));
+        return None;
+    }
+
+    Some(LedgeGrabBox {
+        x1: left.min(right),
+        y1: up.min(down),
+        x2: left.max(right),
+        y2: up.max(down),
+    })
+}
+
+/// The velocity of the fighter on a frame, relative to the direction it is facing.
+/// Velocity added by the frame is added to the animation velocity as the velocity from earlier frames is unknown.
+fn movement(hl_frame: &HighLevelFrame) -> (f32, f32) {
+    let vel = |vel_modify: &BrawlVelModify, vel_temp: f32| match vel_modify {
+        BrawlVelModify::Set (vel) => *vel,
+        BrawlVelModify::Add (vel) => vel_temp + vel,
+        BrawlVelModify::None      => vel_temp,
+    };
+    (vel(&hl_frame.x_vel_modify, hl_frame.x_vel_temp), vel(&hl_frame.y_vel_modify, hl_frame.y_vel_temp))
+}
+
+/// Colboxes are drawn in order of their brawl x position, which is the depth axis.
+/// A NaN depth cannot be sorted so the colbox is drawn behind every other colbox instead.
+fn render_depth(depth: f32, subaction: &str, warnings: &mut Warnings) -> f32 {
+    if depth.is_nan() {
+        warnings.subaction(subaction, String::from("colbox has a NaN depth, drawn behind the other colboxes"));
+        std::f32::NEG_INFINITY
+    } else {
+        depth
+    }
+}
+
+/// Writes the package to an arbitrary directory using the same layout PF Sandbox uses for its own package directory.
+/// Any existing package at out_path is replaced.
+/// Only the files and directories of a package are removed, a non-empty directory that does not contain a package is refused.
+fn save_package_to(package: &Package, out_path: &Path) -> io::Result<()> {
+    if out_path.join("package_meta.json").is_file() {
+        for dir in &["Fighters", "Stages"] {
+            let path = out_path.join(dir);
+            if path.exists() {
+                fs::remove_dir_all(path)?;
+            }
         }
+        for file in &["package_meta.json", "rules.json"] {
+            let path = out_path.join(file);
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+    }
+    else if out_path.exists() && fs::read_dir(out_path)?.next().is_some() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, "directory is not empty and does not contain a package"));
+    }
+    fs::create_dir_all(out_path.join("Fighters"))?;
+    fs::create_dir_all(out_path.join("Stages"))?;
+
+    write_json(&out_path.join("package_meta.json"), &package.meta)?;
+    write_json(&out_path.join("rules.json"), &package.rules)?;
+    for (key, fighter) in package.fighters.key_value_iter() {
+        write_json(&out_path.join("Fighters").join(format!("{}.json", key)), fighter)?;
+    }
+    for (key, stage) in package.stages.key_value_iter() {
+        write_json(&out_path.join("Stages").join(format!("{}.json", key)), stage)?;
     }
+    Ok(())
+}
 
-    package.meta.title = mod_dir.unwrap_or(String::from("Brawl"));
-    package.save();
+/// Unlike Package::save, serialization and write errors are returned instead of panicking
+fn write_json<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(value)?;
+    fs::write(path, json)
+}
+
+fn panic_message(payload: &Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("unknown panic")
+    }
 }
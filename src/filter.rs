@@ -0,0 +1,155 @@
+use brawllib_rs::fighter::{Fighter as BrawlFighter, ModType};
+
+use crate::cli::FilterArgs;
+
+/// Fighters excluded unless they are explicitly named
+const DEFAULT_EXCLUDES: &[&str] = &["poketrainer"];
+
+pub(crate) enum Selection {
+    Export,
+    Skip (String),
+}
+
+/// Selects fighters by lowercase name using glob patterns, `*` matches any sequence of characters and `?` matches any single character.
+/// `all` is accepted as an alias for `*`
+pub(crate) struct FighterFilter {
+    include:            Vec<String>,
+    exclude:            Vec<String>,
+    include_unmodified: bool,
+}
+
+impl FighterFilter {
+    pub(crate) fn new(args: &FilterArgs) -> FighterFilter {
+        FighterFilter {
+            include:            args.include.iter().map(|x| if x == "all" { String::from("*") } else { x.to_lowercase() }).collect(),
+            exclude:            args.exclude.iter().map(|x| x.to_lowercase()).collect(),
+            include_unmodified: args.include_unmodified,
+        }
+    }
+
+    /// Determine if the fighter is exported
+    /// Explicitly naming a fighter overrides the default excludes and the unmodified fighter filter but not --exclude
+    pub(crate) fn select(&self, brawl_fighter: &BrawlFighter, has_mod: bool) -> Selection {
+        let name = brawl_fighter.cased_name.to_lowercase();
+
+        if let Some(pattern) = self.exclude.iter().find(|x| glob_match(x, &name)) {
+            return Selection::Skip (format!("excluded by {}", pattern));
+        }
+
+        if self.include.iter().any(|x| *x == name) {
+            return Selection::Export;
+        }
+
+        if !self.include.iter().any(|x| glob_match(x, &name)) {
+            return Selection::Skip (String::from("not requested"));
+        }
+
+        if let Some(pattern) = DEFAULT_EXCLUDES.iter().find(|x| glob_match(x, &name)) {
+            return Selection::Skip (format!("excluded by default exclude {}", pattern));
+        }
+
+        // Filter unmodified fighters from mods, so that deleted fighters from mods don't show up as brawl fighters
+        let unmodified_fighter_in_mod = match brawl_fighter.mod_type {
+            ModType::NotMod         => true,
+            ModType::ModFromBase    => false,
+            ModType::ModFromScratch => false,
+        } && has_mod;
+        if unmodified_fighter_in_mod && !self.include_unmodified {
+            return Selection::Skip (String::from("unmodified fighter in mod"));
+        }
+
+        Selection::Export
+    }
+
+    /// The explicitly named fighters that are not one of brawl_fighters, usually a typo
+    pub(crate) fn unmatched(&self, brawl_fighters: &[BrawlFighter]) -> Vec<String> {
+        self.include.iter()
+            .filter(|x| !x.contains(|c| c == '*' || c == '?'))
+            .filter(|name| !brawl_fighters.iter().any(|x| x.cased_name.to_lowercase() == **name))
+            .cloned()
+            .collect()
+    }
+}
+
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let mut p = 0;
+    let mut n = 0;
+    // position in the pattern after the last seen `*` and the position in name it is currently matching up to
+    let mut backtrack = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            p += 1;
+            backtrack = Some((p, n));
+        } else if let Some((star_p, star_n)) = backtrack {
+            p = star_p;
+            n = star_n + 1;
+            backtrack = Some((star_p, n));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|x| *x == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_match;
+
+    #[test]
+    fn glob_exact() {
+        assert!(glob_match("link", "link"));
+        assert!(!glob_match("link", "toonlink"));
+        assert!(!glob_match("link", "lin"));
+    }
+
+    #[test]
+    fn glob_leading_star() {
+        assert!(glob_match("*link", "link"));
+        assert!(glob_match("*link", "toonlink"));
+        assert!(!glob_match("*link", "linkx"));
+    }
+
+    #[test]
+    fn glob_trailing_star() {
+        assert!(glob_match("poke*", "poketrainer"));
+        assert!(glob_match("poke*", "poke"));
+        assert!(!glob_match("poke*", "pok"));
+    }
+
+    #[test]
+    fn glob_question_mark() {
+        assert!(glob_match("?ario", "mario"));
+        assert!(glob_match("?ario", "wario"));
+        assert!(!glob_match("?ario", "ario"));
+        assert!(!glob_match("?ario", "marioo"));
+    }
+
+    #[test]
+    fn glob_double_star() {
+        assert!(glob_match("**", ""));
+        assert!(glob_match("**", "mario"));
+        assert!(glob_match("m**o", "mario"));
+        assert!(glob_match("m**o", "mo"));
+    }
+
+    #[test]
+    fn glob_empty() {
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "mario"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("?", ""));
+    }
+
+    #[test]
+    fn glob_backtracking() {
+        assert!(glob_match("*a*b", "aaxab"));
+        assert!(!glob_match("*a*b", "aaxa"));
+    }
+}
@@ -0,0 +1,208 @@
+use brawllib_rs::high_level_fighter::{CollisionBoxValues, HitBoxValues};
+use brawllib_rs::script_ast::{AngleFlip, HitBoxEffect};
+use pf_sandbox_lib::fighter::{CollisionBoxRole, HitBox, HitStun, HitboxEffect};
+
+use crate::profile::Mechanics;
+use crate::warnings::Warnings;
+
+/// The role of a brawl hitbox or grabbox
+/// movement is the x and y velocity of the attacker on this frame
+/// Returns None if the hitbox is not exported
+pub(crate) fn role(values: &CollisionBoxValues, movement: (f32, f32), mechanics: &Mechanics, subaction: &str, warnings: &mut Warnings) -> Option<CollisionBoxRole> {
+    match values {
+        CollisionBoxValues::Hit (values) => hit_box(values, movement, mechanics, subaction, warnings).map(CollisionBoxRole::Hit),
+        CollisionBoxValues::Grab (_)     => Some(CollisionBoxRole::Grab),
+    }
+}
+
+/// The role of the interpolated end of a hitbox that moved since the previous frame.
+/// The values of a hitbox can change between frames, in that case the previous end uses the previous values
+/// so that a sweeping hitbox does not give its old position the damage of its new position.
+pub(crate) fn prev_role(
+    prev: &CollisionBoxValues, next: &CollisionBoxValues, next_role: CollisionBoxRole, movement: (f32, f32), mechanics: &Mechanics, subaction: &str, warnings: &mut Warnings
+) -> CollisionBoxRole {
+    let differ = match (prev, next) {
+        (CollisionBoxValues::Hit (prev), CollisionBoxValues::Hit (next)) => values_differ(prev, next),
+        (CollisionBoxValues::Grab (_),   CollisionBoxValues::Grab (_))   => false,
+        _ => true,
+    };
+    if !differ {
+        return next_role;
+    }
+
+    // The previous values already reported their warnings when their own frame was converted
+    match role(prev, movement, mechanics, subaction, &mut Warnings::new()) {
+        Some(role) => {
+            warnings.subaction(subaction, String::from("hitbox values changed between frames, the interpolated hitbox uses the previous values"));
+            role
+        }
+        None => {
+            warnings.subaction(subaction, String::from("hitbox was not exported on the previous frame, the interpolated hitbox uses the current values"));
+            next_role
+        }
+    }
+}
+
+/// Returns true if any of the values used by hit_box differ
+fn values_differ(prev: &HitBoxValues, next: &HitBoxValues) -> bool {
+    prev.damage            != next.damage            ||
+    prev.trajectory        != next.trajectory        ||
+    prev.wdsk              != next.wdsk              ||
+    prev.kbg               != next.kbg               ||
+    prev.bkb               != next.bkb               ||
+    prev.shield_damage     != next.shield_damage     ||
+    prev.enabled           != next.enabled           ||
+    prev.clang             != next.clang             ||
+    prev.direct            != next.direct            ||
+    prev.hitlag_mult       != next.hitlag_mult       ||
+    prev.sdi_mult          != next.sdi_mult          ||
+    prev.ground            != next.ground            ||
+    prev.aerial            != next.aerial            ||
+    prev.can_hit_fighter() != next.can_hit_fighter() ||
+    prev.effect            != next.effect            ||
+    prev.angle_flipping    != next.angle_flipping
+}
+
+/// Convert the values of a brawl hitbox into a PF Sandbox hitbox
+/// Returns None if the hitbox is disabled.
+/// Returns None and reports the hitbox if it cannot hit fighters, PF Sandbox has no items or projectiles for these to hit.
+pub(crate) fn hit_box(values: &HitBoxValues, movement: (f32, f32), mechanics: &Mechanics, subaction: &str, warnings: &mut Warnings) -> Option<HitBox> {
+    if !values.enabled {
+        return None;
+    }
+    if !values.can_hit_fighter() {
+        warnings.subaction(subaction, String::from("hitbox that can only hit items or projectiles is not exported"));
+        return None;
+    }
+
+    if values.ground != values.aerial {
+        let only = if values.ground { "grounded" } else { "aerial" };
+        warnings.subaction(subaction, format!("hitbox can only hit {} fighters, PF Sandbox hitboxes hit both", only));
+    }
+
+    let enable_reverse_hit = if let AngleFlip::AwayFromAttacker = values.angle_flipping { true } else { false };
+    let trajectory = trajectory(values.trajectory as i32, movement, subaction, warnings);
+    let angle = if let AngleFlip::AttackerDirReverse = values.angle_flipping { 180.0 - trajectory } else { trajectory };
+    let (bkb, kbg) = if values.wdsk == 0 {
+        (values.bkb as f32, values.kbg as f32 / 100.0)
+    } else {
+        let knockback = set_knockback(values.wdsk as f32, values.bkb as f32, values.kbg as f32);
+        warnings.subaction(subaction, format!("weight dependent set knockback {} approximated as fixed knockback {:.1}", values.wdsk, knockback));
+        (knockback, 0.0)
+    };
+
+    Some(HitBox {
+        shield_damage:      values.shield_damage as f32,
+        damage:             values.damage as f32,
+        bkb,
+        kbg,
+        hitstun:            hitstun(values, mechanics, subaction, warnings),
+        enable_clang:       values.clang,
+        enable_rebound:     rebound(values),
+        effect:             effect(&values.effect, subaction, warnings),
+        enable_reverse_hit,
+        angle,
+    })
+}
+
+/// Brawl hitstun is a multiple of the knockback dealt, flinchless hitboxes deal no hitstun at all.
+/// Hitlag and SDI multipliers have no per hitbox equivalent in PF Sandbox so they are reported instead.
+fn hitstun(values: &HitBoxValues, mechanics: &Mechanics, subaction: &str, warnings: &mut Warnings) -> HitStun {
+    if values.hitlag_mult != 1.0 {
+        warnings.subaction(subaction, format!("hitlag multiplier {} is not supported, using 1", values.hitlag_mult));
+    }
+    if values.sdi_mult != 1.0 {
+        warnings.subaction(subaction, format!("SDI multiplier {} is not supported, using 1", values.sdi_mult));
+    }
+
+    if let HitBoxEffect::Flinchless = values.effect {
+        HitStun::Frames (0)
+    } else {
+        HitStun::FramesTimesKnockback (mechanics.hitstun_mult)
+    }
+}
+
+/// The weight used when a weight dependent value needs to be fixed, brawl weights are around 100
+const REFERENCE_WEIGHT: f32 = 100.0;
+
+/// Weight dependent set knockback ignores the percent of the defender and the damage of the hit,
+/// instead the knockback formula uses wdsk as the damage and 10 as the percent.
+/// PF Sandbox has no equivalent so we calculate the knockback dealt to a fighter of REFERENCE_WEIGHT
+/// which becomes a fixed knockback via bkb with no knockback growth.
+fn set_knockback(wdsk: f32, bkb: f32, kbg: f32) -> f32 {
+    let percent = 10.0;
+    ((((percent / 10.0 + percent * wdsk / 20.0) * 200.0 / (REFERENCE_WEIGHT + 100.0) * 1.4) + 18.0) * kbg / 100.0) + bkb
+}
+
+/// The angle a fighter launched by a Sakurai angle hitbox ends up at once it has enough knockback to leave the ground
+const SAKURAI_ANGLE: f32 = 40.0;
+/// Movement slower than this does not give autolink hitboxes a direction
+const AUTOLINK_MIN_SPEED: f32 = 0.001;
+
+/// Brawl uses values over 360 as special trajectories, PF Sandbox has no equivalent for these so they are approximated with a fixed angle.
+/// Autolink hitboxes pull fighters along with the attacker into the next hit of a multihit move,
+/// so they are approximated with the direction the attacker is moving in.
+fn trajectory(trajectory: i32, movement: (f32, f32), subaction: &str, warnings: &mut Warnings) -> f32 {
+    let (x_vel, y_vel) = movement;
+    match trajectory {
+        0 ..= 360 => trajectory as f32,
+        361 => {
+            warnings.subaction(subaction, format!("Sakurai angle approximated as {}", SAKURAI_ANGLE));
+            SAKURAI_ANGLE
+        }
+        363 | 365 | 366 if (x_vel * x_vel + y_vel * y_vel).sqrt() > AUTOLINK_MIN_SPEED => {
+            let angle = y_vel.atan2(x_vel).to_degrees();
+            let angle = if angle < 0.0 { angle + 360.0 } else { angle };
+            warnings.subaction(subaction, format!("autolink angle {} approximated as the attackers movement direction {:.0}", trajectory, angle));
+            angle
+        }
+        _ => {
+            let angle = ((trajectory % 360 + 360) % 360) as f32;
+            warnings.subaction(subaction, format!("unknown special angle {} approximated as {}", trajectory, angle));
+            angle
+        }
+    }
+}
+
+/// In brawl the clang flag allows a hitbox to trade with other hitboxes.
+/// When the trade occurs, the attacker only rebounds (cancelling their attack) if the hitbox is direct i.e. part of the fighter.
+/// Indirect hitboxes such as projectiles and articles just trade without affecting the fighter that created them.
+fn rebound(values: &HitBoxValues) -> bool {
+    values.clang && values.direct
+}
+
+/// Brawl has many more hitbox effects than PF Sandbox.
+/// Effects that only change the graphics or sound of a hit become HitboxEffect::None silently,
+/// any other effect without an equivalent is reported and approximated.
+fn effect(effect: &HitBoxEffect, subaction: &str, warnings: &mut Warnings) -> HitboxEffect {
+    let (result, approximated) = match effect {
+        HitBoxEffect::Normal   => (HitboxEffect::None,     false),
+        HitBoxEffect::None     => (HitboxEffect::None,     false),
+        HitBoxEffect::Slash    => (HitboxEffect::None,     false),
+        // Flinchless is handled by the hitstun
+        HitBoxEffect::Flinchless => (HitboxEffect::None,   false),
+        HitBoxEffect::Electric => (HitboxEffect::Electric, false),
+        HitBoxEffect::Freezing => (HitboxEffect::Freeze,   false),
+        HitBoxEffect::Flame    => (HitboxEffect::Fire,     false),
+        HitBoxEffect::Reverse  => (HitboxEffect::Reverse,  false),
+        HitBoxEffect::Sleep    => (HitboxEffect::Sleep,    false),
+        HitBoxEffect::Stun     => (HitboxEffect::Stun,     false),
+        HitBoxEffect::Paralyze => (HitboxEffect::Stun,     true),
+        HitBoxEffect::Darkness => (HitboxEffect::Fire,     true),
+        HitBoxEffect::Coin       |
+        HitBoxEffect::Trip       |
+        HitBoxEffect::Bury       |
+        HitBoxEffect::Flower     |
+        HitBoxEffect::Grass      |
+        HitBoxEffect::Water      |
+        HitBoxEffect::Aura       |
+        HitBoxEffect::Plunge     |
+        HitBoxEffect::Down       |
+        HitBoxEffect::Unknown (_) => (HitboxEffect::None, true),
+    };
+
+    if approximated {
+        warnings.subaction(subaction, format!("hitbox effect {:?} is not supported, using {:?}", effect, result));
+    }
+    result
+}
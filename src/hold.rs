@@ -0,0 +1,41 @@
+use brawllib_rs::fighter::Fighter as BrawlFighter;
+use brawllib_rs::high_level_fighter::HighLevelHurtBox;
+
+use crate::bones::BoneChain;
+use crate::warnings::Warnings;
+
+/// Bone that held items are attached to
+const ITEM_BONE: &str = "HaveN";
+/// Bone that grabbed fighters are attached to
+const GRAB_BONE: &str = "ThrowN";
+
+/// Finds where held items and grabbed fighters are positioned on each frame.
+/// The hold bones never have a hurtbox so their position comes from the nearest ancestor that does, usually the hand.
+pub(crate) struct HoldBones {
+    item: Option<BoneChain>,
+    grab: Option<BoneChain>,
+}
+
+impl HoldBones {
+    pub(crate) fn new(brawl_fighter: &BrawlFighter, warnings: &mut Warnings) -> HoldBones {
+        let item = BoneChain::new(brawl_fighter, ITEM_BONE);
+        let grab = BoneChain::new(brawl_fighter, GRAB_BONE);
+        if item.is_none() {
+            warnings.fighter(format!("no {} bone, using the default item hold position", ITEM_BONE));
+        }
+        if grab.is_none() {
+            warnings.fighter(format!("no {} bone, using the default grab hold position", GRAB_BONE));
+        }
+        HoldBones { item, grab }
+    }
+
+    /// The item hold position for the frame with the given hurtboxes
+    pub(crate) fn item_hold(&self, hurt_boxes: &[HighLevelHurtBox]) -> Option<(f32, f32)> {
+        self.item.as_ref().and_then(|x| x.position(hurt_boxes))
+    }
+
+    /// The grab hold position for the frame with the given hurtboxes
+    pub(crate) fn grab_hold(&self, hurt_boxes: &[HighLevelHurtBox]) -> Option<(f32, f32)> {
+        self.grab.as_ref().and_then(|x| x.position(hurt_boxes))
+    }
+}
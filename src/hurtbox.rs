@@ -0,0 +1,367 @@
+use brawllib_rs::high_level_fighter::{HighLevelFighter, HighLevelHurtBox};
+use brawllib_rs::sakurai::fighter_data::misc_section::HurtBoxZone;
+use brawllib_rs::script_ast::HurtBoxState;
+use cgmath::{InnerSpace, Matrix4, Vector2, Vector3};
+use pf_sandbox_lib::fighter::{CollisionBox, CollisionBoxLink, CollisionBoxRole, HurtBox, LinkType, RenderOrder};
+
+use crate::warnings::Warnings;
+
+/// Projected lengths smaller than this are treated as zero
+const EPSILON: f32 = 0.001;
+/// Maximum number of parallel linked colboxes used to cover a wide hurtbox
+const MAX_LINES: usize = 4;
+
+/// The role of a hurtbox from its current state.
+/// Brawl hurtboxes have no damage or knockback modifiers and their zone only selects which damage animation is used when hit,
+/// so every hurtbox uses the default PF Sandbox hurtbox values.
+pub(crate) fn role(hurt_box: &HighLevelHurtBox) -> CollisionBoxRole {
+    match hurt_box.state {
+        HurtBoxState::Unknown(_) |
+        HurtBoxState::Normal => CollisionBoxRole::Hurt(HurtBox::default()),
+        HurtBoxState::Invincible => CollisionBoxRole::Invincible,
+        HurtBoxState::IntangibleFlashing |
+        HurtBoxState::IntangibleNoFlashing |
+        HurtBoxState::IntangibleQuickFlashing => CollisionBoxRole::Intangible,
+    }
+}
+
+/// Report hurtboxes whose zone is unknown, the hurtboxes are the same on every frame so only the first frame is checked
+pub(crate) fn check_zones(hl_fighter: &HighLevelFighter, warnings: &mut Warnings) {
+    let frame = hl_fighter.subactions.iter().flat_map(|x| x.frames.iter()).next();
+    if let Some(frame) = frame {
+        for hurt_box in &frame.hurt_boxes {
+            if let HurtBoxZone::Unknown (value) = hurt_box.hurt_box.zone {
+                warnings.fighter(format!("hurtbox on bone {} has unknown zone {}, treated as a normal hurtbox", hurt_box.hurt_box.bone_index, value));
+            }
+        }
+    }
+}
+
+/// Creates the colboxes and links for a brawl hurtbox, returning the render order elements and the depth to sort them by.
+///
+/// A brawl hurtbox is a box with corners at offset and offset + the stretch along each axis, expanded by radius in every direction.
+/// The box is projected onto the plane of play, see `Shape` for how the projection is covered.
+pub(crate) fn hurt_box(
+    hurt_box: &HighLevelHurtBox, role: CollisionBoxRole, colboxes: &mut Vec<CollisionBox>, colbox_links: &mut Vec<CollisionBoxLink>
+) -> (Vec<RenderOrder>, f32) {
+    let transform = hurt_box.bone_matrix * Matrix4::<f32>::from_translation(hurt_box.hurt_box.offset);
+    let radius = hurt_box.hurt_box.radius * scale(&transform);
+    let projection = Projection::new(&transform, hurt_box.hurt_box.stretch);
+
+    match projection.shape(radius) {
+        Shape::Circle { center, radius } => {
+            colboxes.push(CollisionBox {
+                point: (center.x, center.y),
+                radius,
+                role,
+            });
+            (vec!(RenderOrder::Colbox(colboxes.len() - 1)), projection.depth)
+        }
+        Shape::Lines { lines, radius } => {
+            let mut render_order = vec!();
+            for (start, end) in lines {
+                colboxes.push(CollisionBox {
+                    point: (start.x, start.y),
+                    radius,
+                    role: role.clone(),
+                });
+                colboxes.push(CollisionBox {
+                    point: (end.x, end.y),
+                    radius,
+                    role: role.clone(),
+                });
+                colbox_links.push(CollisionBoxLink {
+                    one: colboxes.len() - 2,
+                    two: colboxes.len() - 1,
+                    link_type: LinkType::MeldFirst,
+                });
+                render_order.push(RenderOrder::Link(colbox_links.len() - 1));
+            }
+            (render_order, projection.depth)
+        }
+    }
+}
+
+/// The start and end of a linked pair of colboxes
+type Line = (Vector2<f32>, Vector2<f32>);
+
+/// The colboxes covering a projected hurtbox
+#[derive(Debug)]
+enum Shape {
+    /// A single circle, used when the projection is small compared to the radius
+    Circle { center: Vector2<f32>, radius: f32 },
+    /// Parallel linked pairs of colboxes running along the length of the projection, spread across its width
+    Lines { lines: Vec<Line>, radius: f32 },
+}
+
+/// A hurtbox box projected onto the plane of play
+struct Projection {
+    /// The projected corners of the box
+    corners: Vec<Vector2<f32>>,
+    /// The projected edges of the box, one for each stretched axis
+    edges:   Vec<Vector2<f32>>,
+    /// The average depth of the corners
+    depth:   f32,
+}
+
+impl Projection {
+    /// Corners and edges are only created along axes that are stretched.
+    fn new(transform: &Matrix4<f32>, stretch: Vector3<f32>) -> Projection {
+        let xs: &[f32] = if stretch.x != 0.0 { &[0.0, 1.0] } else { &[0.0] };
+        let ys: &[f32] = if stretch.y != 0.0 { &[0.0, 1.0] } else { &[0.0] };
+        let zs: &[f32] = if stretch.z != 0.0 { &[0.0, 1.0] } else { &[0.0] };
+
+        let mut corners = vec!();
+        let mut depth = 0.0;
+        for x in xs {
+            for y in ys {
+                for z in zs {
+                    let (corner, corner_depth) = project(transform, Vector3::new(stretch.x * x, stretch.y * y, stretch.z * z));
+                    corners.push(corner);
+                    depth += corner_depth;
+                }
+            }
+        }
+        let depth = depth / corners.len() as f32;
+
+        let origin = corners[0];
+        let mut edges = vec!();
+        for &axis in &[Vector3::new(stretch.x, 0.0, 0.0), Vector3::new(0.0, stretch.y, 0.0), Vector3::new(0.0, 0.0, stretch.z)] {
+            if axis != Vector3::new(0.0, 0.0, 0.0) {
+                edges.push(project(transform, axis).0 - origin);
+            }
+        }
+
+        Projection { corners, edges, depth }
+    }
+
+    /// The projection of a brawl box is a convex polygon whose sides are parallel to the projected edges,
+    /// so the minimum area rectangle containing it is aligned to one of those edges.
+    /// The silhouette is then covered by:
+    /// *   a single circle when the rectangle is small compared to the radius
+    /// *   a linked pair of colboxes along the length of the rectangle when it is thin
+    /// *   multiple parallel linked pairs when it is wide
+    fn shape(&self, radius: f32) -> Shape {
+        let origin = self.corners[0];
+
+        let mut best: Option<Rectangle> = None;
+        for edge in &self.edges {
+            let edge_length = edge.magnitude();
+            if edge_length.is_nan() || edge_length <= EPSILON {
+                continue;
+            }
+            let along = *edge / edge_length;
+            let across = Vector2::new(-along.y, along.x);
+
+            let mut min_along = std::f32::INFINITY;
+            let mut max_along = std::f32::NEG_INFINITY;
+            let mut min_across = std::f32::INFINITY;
+            let mut max_across = std::f32::NEG_INFINITY;
+            for corner in &self.corners {
+                let relative = *corner - origin;
+                min_along = min_along.min(relative.dot(along));
+                max_along = max_along.max(relative.dot(along));
+                min_across = min_across.min(relative.dot(across));
+                max_across = max_across.max(relative.dot(across));
+            }
+
+            // Run the lines along the longer side of the rectangle
+            let rectangle = if max_along - min_along >= max_across - min_across {
+                Rectangle { along, across, min_along, max_along, min_across, max_across }
+            } else {
+                Rectangle { along: across, across: -along, min_along: min_across, max_along: max_across, min_across: -max_along, max_across: -min_along }
+            };
+            if best.as_ref().map(|x| rectangle.area() < x.area()).unwrap_or(true) {
+                best = Some(rectangle);
+            }
+        }
+
+        let Rectangle { along, across, min_along, max_along, min_across, max_across } = match best {
+            Some(best) => best,
+            None => return Shape::Circle { center: origin, radius },
+        };
+        let length = max_along - min_along;
+        let width = max_across - min_across;
+
+        let diagonal = (length * length + width * width).sqrt();
+        if diagonal <= radius {
+            let center = origin + along * (min_along + max_along) / 2.0 + across * (min_across + max_across) / 2.0;
+            return Shape::Circle { center, radius: radius + diagonal / 2.0 };
+        }
+
+        // Space the lines at most radius apart so the silhouette has no dents between them
+        let count = if width > EPSILON {
+            (width / radius).ceil().min(MAX_LINES as f32 - 1.0) as usize + 1
+        } else {
+            1
+        };
+
+        let mut lines = vec!();
+        for line in 0..count {
+            let offset = if count == 1 {
+                (min_across + max_across) / 2.0
+            } else {
+                min_across + width * line as f32 / (count - 1) as f32
+            };
+            let start = origin + along * min_along + across * offset;
+            let end   = origin + along * max_along + across * offset;
+            lines.push((start, end));
+        }
+        Shape::Lines { lines, radius }
+    }
+}
+
+/// A rectangle containing the projection, the sides run along and across and are given as distances from the origin corner
+struct Rectangle {
+    along:      Vector2<f32>,
+    across:     Vector2<f32>,
+    min_along:  f32,
+    max_along:  f32,
+    min_across: f32,
+    max_across: f32,
+}
+
+impl Rectangle {
+    fn area(&self) -> f32 {
+        (self.max_along - self.min_along) * (self.max_across - self.min_across)
+    }
+}
+
+/// Returns the position of offset from transform on the plane of play and its depth.
+/// The brawl z axis is the horizontal axis of the plane of play, x is the depth
+fn project(transform: &Matrix4<f32>, offset: Vector3<f32>) -> (Vector2<f32>, f32) {
+    let point = *transform * Matrix4::<f32>::from_translation(offset);
+    (Vector2::new(point.w.z, point.w.y), point.w.x)
+}
+
+/// Bones can be scaled, the hurtbox radius is scaled by the largest axis so the hurtbox is never smaller than in brawl
+fn scale(transform: &Matrix4<f32>) -> f32 {
+    transform.x.truncate().magnitude()
+        .max(transform.y.truncate().magnitude())
+        .max(transform.z.truncate().magnitude())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, SquareMatrix};
+
+    fn expect_lines(shape: Shape) -> (Vec<Line>, f32) {
+        match shape {
+            Shape::Lines { lines, radius } => (lines, radius),
+            shape => panic!("expected lines, got {:?}", shape),
+        }
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 0.001, "{} != {}", a, b);
+    }
+
+    /// Length and width of the rectangle covered by the lines
+    fn size(lines: &[Line]) -> (f32, f32) {
+        let (start, end) = lines[0];
+        let length = (end - start).magnitude();
+        let width = (lines[lines.len() - 1].0 - start).magnitude();
+        (length, width)
+    }
+
+    #[test]
+    fn unstretched() {
+        let projection = Projection::new(&Matrix4::identity(), Vector3::new(0.0, 0.0, 0.0));
+        match projection.shape(2.0) {
+            Shape::Circle { center, radius } => {
+                assert_close(center.x, 0.0);
+                assert_close(center.y, 0.0);
+                assert_close(radius, 2.0);
+            }
+            shape => panic!("expected a circle, got {:?}", shape),
+        }
+    }
+
+    #[test]
+    fn one_axis() {
+        let projection = Projection::new(&Matrix4::identity(), Vector3::new(0.0, 10.0, 0.0));
+        let (lines, radius) = expect_lines(projection.shape(2.0));
+        assert_eq!(lines.len(), 1);
+        assert_close(radius, 2.0);
+        let (length, width) = size(&lines);
+        assert_close(length, 10.0);
+        assert_close(width, 0.0);
+    }
+
+    #[test]
+    fn one_axis_small() {
+        let projection = Projection::new(&Matrix4::identity(), Vector3::new(0.0, 1.0, 0.0));
+        match projection.shape(2.0) {
+            Shape::Circle { center, radius } => {
+                assert_close(center.x, 0.0);
+                assert_close(center.y, 0.5);
+                assert_close(radius, 2.5);
+            }
+            shape => panic!("expected a circle, got {:?}", shape),
+        }
+    }
+
+    #[test]
+    fn one_axis_depth_only() {
+        let projection = Projection::new(&Matrix4::identity(), Vector3::new(10.0, 0.0, 0.0));
+        assert_close(projection.depth, 5.0);
+        match projection.shape(2.0) {
+            Shape::Circle { radius, .. } => assert_close(radius, 2.0),
+            shape => panic!("expected a circle, got {:?}", shape),
+        }
+    }
+
+    #[test]
+    fn two_axes() {
+        let projection = Projection::new(&Matrix4::identity(), Vector3::new(0.0, 4.0, 10.0));
+        let (lines, radius) = expect_lines(projection.shape(2.0));
+        assert_eq!(lines.len(), 3);
+        assert_close(radius, 2.0);
+        let (length, width) = size(&lines);
+        assert_close(length, 10.0);
+        assert_close(width, 4.0);
+        // runs along the z axis which is the horizontal axis of the plane of play
+        assert_close(lines[0].0.y, lines[0].1.y);
+    }
+
+    #[test]
+    fn two_axes_rotated() {
+        let transform = Matrix4::from_angle_x(Deg(30.0));
+        let projection = Projection::new(&transform, Vector3::new(0.0, 4.0, 10.0));
+        let (lines, _) = expect_lines(projection.shape(2.0));
+        let (length, width) = size(&lines);
+        assert_close(length, 10.0);
+        assert_close(width, 4.0);
+    }
+
+    #[test]
+    fn three_axes() {
+        // the x axis is the depth so only affects the depth
+        let projection = Projection::new(&Matrix4::identity(), Vector3::new(6.0, 4.0, 10.0));
+        assert_close(projection.depth, 3.0);
+        let (lines, _) = expect_lines(projection.shape(2.0));
+        let (length, width) = size(&lines);
+        assert_close(length, 10.0);
+        assert_close(width, 4.0);
+    }
+
+    #[test]
+    fn three_axes_rotated() {
+        // rotating the depth axis into the plane of play
+        let transform = Matrix4::from_angle_y(Deg(90.0));
+        let projection = Projection::new(&transform, Vector3::new(6.0, 4.0, 0.5));
+        let (lines, _) = expect_lines(projection.shape(2.0));
+        let (length, width) = size(&lines);
+        assert_close(length, 6.0);
+        assert_close(width, 4.0);
+
+        // the x and z edges are both projected onto the horizontal axis
+        let transform = Matrix4::from_angle_y(Deg(45.0));
+        let projection = Projection::new(&transform, Vector3::new(6.0, 4.0, 6.0));
+        let (lines, _) = expect_lines(projection.shape(2.0));
+        let (length, width) = size(&lines);
+        assert_close(length, 6.0 * 2.0f32.sqrt());
+        assert_close(width, 4.0);
+    }
+}
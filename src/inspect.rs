@@ -0,0 +1,33 @@
+use brawllib_rs::high_level_fighter::HighLevelFighter;
+use pf_sandbox_lib::fighter::Action;
+use num_traits::FromPrimitive;
+
+use crate::action_map::action_name_to_indexes;
+use crate::cli::DataArgs;
+use crate::export::{self, ExportError};
+
+/// Print the brawl data of the fighter with the lowercase name fighter_name
+pub(crate) fn inspect(data: &DataArgs, fighter_name: &str) -> Result<(), ExportError> {
+    let brawl_fighters = export::load_fighters(data)?;
+    let brawl_fighter = brawl_fighters.iter()
+        .find(|x| x.cased_name.to_lowercase() == fighter_name)
+        .ok_or(ExportError::NoFightersMatched)?;
+    let hl_fighter = HighLevelFighter::new(brawl_fighter);
+
+    println!("Fighter:       {}", brawl_fighter.cased_name);
+    println!("Mod type:      {:?}", brawl_fighter.mod_type);
+    println!("Modded by PSA: {}", brawl_fighter.modded_by_psa);
+    println!("Attributes:    {:#?}", hl_fighter.attributes);
+
+    let name_width = hl_fighter.subactions.iter().map(|x| x.name.len()).max().unwrap_or(0).max("Subaction".len());
+    println!();
+    println!("{:<width$}  {:>6}  {:>4}  {}", "Subaction", "Frames", "IASA", "PF Sandbox Actions", width = name_width);
+    for subaction in &hl_fighter.subactions {
+        let actions: Vec<String> = action_name_to_indexes(&subaction.name).iter()
+            .map(|x| Action::from_usize(*x).map(|x| format!("{:?}", x)).unwrap_or_else(|| x.to_string()))
+            .collect();
+        println!("{:<width$}  {:>6}  {:>4}  {}", subaction.name, subaction.frames.len(), subaction.iasa, actions.join(", "), width = name_width);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Distinguishes the merged directories of ModLayers that exist at the same time, e.g. both sides of a diff
+static MERGED_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// A single mod directory built from a stack of mod directories.
+/// Files from later layers take priority over files from earlier layers.
+/// The merged directory is created in the mods directory so that files can be hard linked instead of copied,
+/// if the mods directory is read only it is created in the temp directory instead.
+/// It is deleted when the ModLayers is dropped, a directory left behind by an interrupted run is replaced by the next run.
+pub(crate) struct ModLayers {
+    path:   Option<PathBuf>,
+    merged: bool,
+}
+
+impl ModLayers {
+    /// layers are ordered from lowest to highest priority
+    pub(crate) fn new(mods_path: &Path, layers: &[String]) -> io::Result<ModLayers> {
+        match layers {
+            [] => Ok(ModLayers { path: None, merged: false }),
+            [layer] => Ok(ModLayers { path: Some(mods_path.join(layer)), merged: false }),
+            _ => {
+                for layer in layers {
+                    let layer_path = mods_path.join(layer);
+                    if !layer_path.is_dir() {
+                        return Err(io::Error::new(io::ErrorKind::NotFound, format!("mod directory {} does not exist", layer_path.to_string_lossy())));
+                    }
+                }
+
+                let name = format!(".brawl_exporter_layers_{}", MERGED_COUNT.fetch_add(1, Ordering::SeqCst));
+                let merged_path = match create_empty_dir(&mods_path.join(&name)) {
+                    Ok(path) => path,
+                    Err(_)   => create_empty_dir(&env::temp_dir().join(&name))?,
+                };
+                // construct before merging so the directory is cleaned up if merging fails
+                let mod_layers = ModLayers { path: Some(merged_path.clone()), merged: true };
+                for layer in layers {
+                    merge_dir(&mods_path.join(layer), &merged_path)?;
+                }
+                Ok(mod_layers)
+            }
+        }
+    }
+
+    /// The directory to use as the mod directory, None if there are no mods
+    pub(crate) fn path(&self) -> Option<&Path> {
+        self.path.as_ref().map(|x| x.as_path())
+    }
+}
+
+impl Drop for ModLayers {
+    fn drop(&mut self) {
+        if let (true, Some(path)) = (self.merged, &self.path) {
+            if let Err(err) = fs::remove_dir_all(path) {
+                eprintln!("Failed to remove merged mod directory {}: {}", path.to_string_lossy(), err);
+            }
+        }
+    }
+}
+
+/// Create an empty directory at path, replacing anything left there by an interrupted run
+fn create_empty_dir(path: &Path) -> io::Result<PathBuf> {
+    if path.exists() {
+        fs::remove_dir_all(path)?;
+    }
+    fs::create_dir(path)?;
+    Ok(path.to_path_buf())
+}
+
+/// Recursively place every file in src into dest, replacing any existing files.
+/// A file replaces a directory of the same name and a directory replaces a file of the same name.
+/// Files are hard linked where possible to avoid copying entire sd cards.
+fn merge_dir(src: &Path, dest: &Path) -> io::Result<()> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let src_path = entry.path();
+        let dest_path = dest.join(entry.file_name());
+        let dest_is_dir = fs::symlink_metadata(&dest_path).ok().map(|x| x.is_dir());
+        if entry.file_type()?.is_dir() {
+            if let Some(false) = dest_is_dir {
+                fs::remove_file(&dest_path)?;
+            }
+            merge_dir(&src_path, &dest_path)?;
+        } else {
+            match dest_is_dir {
+                Some(true)  => fs::remove_dir_all(&dest_path)?,
+                Some(false) => fs::remove_file(&dest_path)?,
+                None        => { }
+            }
+            if fs::hard_link(&src_path, &dest_path).is_err() {
+                fs::copy(&src_path, &dest_path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The package title listing every layer in priority order, lowest first
+pub(crate) fn title(layers: &[String]) -> String {
+    let mut title = String::from("Brawl");
+    for layer in layers {
+        title.push_str(" + ");
+        title.push_str(layer);
+    }
+    title
+}
+
+/// The package directory name for the stack of layers
+pub(crate) fn package_name(layers: &[String]) -> String {
+    if layers.is_empty() {
+        String::from("brawl")
+    } else {
+        layers.join("-")
+    }
+}
@@ -0,0 +1,43 @@
+use brawllib_rs::fighter::ModType;
+use brawllib_rs::high_level_fighter::HighLevelFighter;
+
+use crate::cli::DataArgs;
+use crate::export::{self, ExportError};
+use crate::filter::{FighterFilter, Selection};
+
+/// Print every fighter found in the brawl and mod files along with whether filter would export it
+/// jobs is the number of fighters whose subactions are counted concurrently, None uses the number of cpus
+pub(crate) fn list(data: &DataArgs, filter: &FighterFilter, jobs: Option<usize>) -> Result<(), ExportError> {
+    let has_mod = !data.mod_names.is_empty();
+    let brawl_fighters = export::load_fighters(data)?;
+
+    let subaction_counts = export::map_fighters(&brawl_fighters, jobs, |_, brawl_fighter| Ok(HighLevelFighter::new(brawl_fighter).subactions.len()));
+
+    let name_width = brawl_fighters.iter().map(|x| x.cased_name.len()).max().unwrap_or(0).max("Fighter".len());
+    println!("{:<width$}  {:<14}  {:<5}  {:<10}  {}", "Fighter", "Mod Type", "PSA", "Subactions", "Export", width = name_width);
+    for (brawl_fighter, subaction_count) in brawl_fighters.iter().zip(subaction_counts) {
+        // A fighter with malformed data is still listed so that it can be excluded
+        let subaction_count = match subaction_count {
+            Ok(count) => count.to_string(),
+            Err(err) => {
+                error!("{}", err);
+                String::from("invalid")
+            }
+        };
+        let mod_type = match brawl_fighter.mod_type {
+            ModType::NotMod         => "NotMod",
+            ModType::ModFromBase    => "ModFromBase",
+            ModType::ModFromScratch => "ModFromScratch",
+        };
+        let selection = match filter.select(brawl_fighter, has_mod) {
+            Selection::Export        => String::from("include"),
+            Selection::Skip (reason) => format!("skip ({})", reason),
+        };
+        println!("{:<width$}  {:<14}  {:<5}  {:<10}  {}", brawl_fighter.cased_name, mod_type, brawl_fighter.modded_by_psa, subaction_count, selection, width = name_width);
+    }
+    for name in filter.unmatched(&brawl_fighters) {
+        println!("{} does not match any fighter", name);
+    }
+
+    Ok(())
+}
@@ -1,13 +1,76 @@
 #[macro_use] extern crate log;
 
 mod action_map;
+mod air_jump;
+mod bones;
 mod cli;
+mod diff;
 mod export;
+mod filter;
+mod hitbox;
+mod hold;
+mod hurtbox;
+mod inspect;
+mod layers;
+mod list;
 mod logger;
+mod profile;
+mod report;
+mod settings;
+mod shield;
+mod summary;
+mod validate;
+mod warnings;
+
+use std::process;
+
+use crate::cli::{Command, DataArgs};
+use crate::export::ExportError;
+use crate::filter::FighterFilter;
+use crate::settings::Settings;
 
 fn main() {
     logger::init();
-    if let Some(cli) = cli::parse_cli() {
-        export::export(cli.mod_name, &cli.export_fighters);
+    if let Some(command) = cli::parse_cli() {
+        if let Err(err) = run(command) {
+            eprintln!("{}", err);
+            process::exit(err.exit_code());
+        }
+    }
+    else {
+        process::exit(1);
+    }
+}
+
+fn run(command: Command) -> Result<(), ExportError> {
+    match command {
+        Command::Export { data, filter, settings, profile, out_dir, jobs } => {
+            let settings = Settings::load(settings.as_ref().map(|x| x.as_path()))?;
+            export::export(&data, &settings, &profile, out_dir.as_ref().map(|x| x.as_path()), jobs, &FighterFilter::new(&filter))
+        }
+        Command::List { data, filter, jobs } => {
+            list::list(&data, &FighterFilter::new(&filter), jobs)
+        }
+        Command::Inspect { data, fighter } => {
+            inspect::inspect(&data, &fighter)
+        }
+        Command::Report { data, filter, settings, profile, jobs } => {
+            let settings = Settings::load(settings.as_ref().map(|x| x.as_path()))?;
+            report::report(&data, &settings, &profile, jobs, &FighterFilter::new(&filter))
+        }
+        Command::Validate { data, filter, settings, profile, jobs } => {
+            let settings = Settings::load(settings.as_ref().map(|x| x.as_path()))?;
+            validate::validate(&data, &settings, &profile, jobs, &FighterFilter::new(&filter))
+        }
+        Command::Diff { data, other_mod_names, filter, settings, profile, jobs } => {
+            let settings = Settings::load(settings.as_ref().map(|x| x.as_path()))?;
+            let other_data = DataArgs {
+                mod_names: other_mod_names,
+                brawl_dir: data.brawl_dir.clone(),
+                mods_dir:  data.mods_dir.clone(),
+            };
+            diff::diff(&data, &other_data, &settings, &profile, jobs, &FighterFilter::new(&filter))
+        }
+        Command::Help => Ok(()),
     }
 }
@@ -0,0 +1,103 @@
+use brawllib_rs::fighter::Fighter as BrawlFighter;
+use serde::{Serialize, Deserialize};
+
+use crate::settings::Settings;
+
+/// Selects the set of game mechanics given to every exported fighter
+pub(crate) enum Profile {
+    /// Not always correct, but assumes that psa modded fighters are from PM and everything else is from brawl
+    Auto,
+    Brawl,
+    ProjectM,
+    /// Uses the `[custom_profile]` table of the settings file
+    Custom,
+}
+
+impl Profile {
+    pub(crate) fn from_name(name: &str) -> Option<Profile> {
+        match name.to_lowercase().as_ref() {
+            "auto"   => Some(Profile::Auto),
+            "brawl"  => Some(Profile::Brawl),
+            "pm"     => Some(Profile::ProjectM),
+            "custom" => Some(Profile::Custom),
+            _        => None
+        }
+    }
+
+    /// The mechanics to use for brawl_fighter
+    /// The fighter's settings override the values of the selected profile
+    pub(crate) fn mechanics(&self, brawl_fighter: &BrawlFighter, settings: &Settings) -> Mechanics {
+        let mut mechanics = match self {
+            Profile::Auto     => if brawl_fighter.modded_by_psa { Mechanics::project_m() } else { Mechanics::brawl() },
+            Profile::Brawl    => Mechanics::brawl(),
+            Profile::ProjectM => Mechanics::project_m(),
+            Profile::Custom   => settings.custom_profile.clone(),
+        };
+        settings.fighter(&brawl_fighter.cased_name).override_mechanics(&mut mechanics);
+        mechanics
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Mechanics {
+    /// L-cancelling is disabled when 0
+    pub lcancel_window:     u64,
+    pub lcancel_frame_skip: u64,
+    /// Velocity multiplier applied when airdodging, 0 keeps the fighters current momentum
+    pub aerialdodge_mult:   f32,
+    pub tech:               bool,
+    pub power_shield:       PowerShieldMechanics,
+    /// hitstun frames = knockback * hitstun_mult
+    pub hitstun_mult:       f32,
+}
+
+impl Default for Mechanics {
+    fn default() -> Mechanics {
+        Mechanics::brawl()
+    }
+}
+
+impl Mechanics {
+    pub(crate) fn brawl() -> Mechanics {
+        Mechanics {
+            lcancel_window:     0,
+            lcancel_frame_skip: 0,
+            aerialdodge_mult:   0.0,
+            tech:               true,
+            power_shield:       PowerShieldMechanics::default(),
+            hitstun_mult:       0.4,
+        }
+    }
+
+    pub(crate) fn project_m() -> Mechanics {
+        Mechanics {
+            lcancel_window:     7,
+            lcancel_frame_skip: 1,
+            aerialdodge_mult:   3.0,
+            tech:               true,
+            power_shield:       PowerShieldMechanics::default(),
+            hitstun_mult:       0.4,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct PowerShieldMechanics {
+    /// The reflect window is disabled when 0
+    pub reflect_window: u64,
+    /// The parry is disabled when 0
+    pub parry_window:   u64,
+    pub parry_duration: u64,
+}
+
+impl Default for PowerShieldMechanics {
+    fn default() -> PowerShieldMechanics {
+        PowerShieldMechanics {
+            reflect_window: 2,
+            parry_window:   4,
+            parry_duration: 4,
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use crate::cli::DataArgs;
+use crate::export::{self, ExportError};
+use crate::filter::FighterFilter;
+use crate::profile::Profile;
+use crate::settings::Settings;
+
+/// Convert all fighters selected by filter without saving and report how complete the conversion is and what data was lost
+pub(crate) fn report(data: &DataArgs, settings: &Settings, profile: &Profile, jobs: Option<usize>, filter: &FighterFilter) -> Result<(), ExportError> {
+    let mut conversion = export::convert(data, settings, profile, jobs, filter)?;
+    conversion.summary.print();
+    conversion.require_fighters()?;
+
+    let name_width = conversion.fighters.iter().map(|x| x.name.len()).max().unwrap_or(0).max("Fighter".len());
+    println!();
+    println!("{:<width$}  {:>7}  {:>6}  {:>8}", "Fighter", "Actions", "Frames", "Warnings", width = name_width);
+    for converted in &conversion.fighters {
+        let fighter = &converted.fighter;
+        let populated = fighter.actions.iter().filter(|x| x.frames.len() > 0).count();
+        let frames: usize = fighter.actions.iter().map(|x| x.frames.len()).sum();
+        println!("{:<width$}  {:>7}  {:>6}  {:>8}", converted.name, format!("{}/{}", populated, fighter.actions.len()), frames, converted.warnings.len(), width = name_width);
+    }
+
+    for converted in &conversion.fighters {
+        if converted.warnings.len() > 0 {
+            println!();
+            println!("{} warnings:", converted.name);
+            for warning in converted.warnings.iter() {
+                let location = warning.subaction.as_ref().map(|x| x.as_str()).unwrap_or("fighter");
+                if warning.count > 1 {
+                    println!("    {}: {} (x{})", location, warning.message, warning.count);
+                } else {
+                    println!("    {}: {}", location, warning.message);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
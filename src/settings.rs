@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+use toml::Value;
+
+use crate::export::ExportError;
+use crate::profile::Mechanics;
+
+/// Values used when converting fighters that brawl does not provide.
+/// Loaded from a toml file where the `[global]` table overrides the defaults for every fighter
+/// and `[fighters.NAME]` tables override the global values for the fighter with lowercase name NAME.
+/// The `[custom_profile]` table overrides the brawl mechanics for the custom profile.
+pub(crate) struct Settings {
+    global:                    FighterSettings,
+    fighters:                  HashMap<String, FighterSettings>,
+    pub(crate) custom_profile: Mechanics,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct FighterSettings {
+    pub shield:                   ShieldSettings,
+    pub missed_tech_forced_getup: u64,
+    /// Hold positions used when the fighter has no hold bones
+    pub item_hold_x:              f32,
+    pub item_hold_y:              f32,
+    pub grab_hold_x:              f32,
+    pub grab_hold_y:              f32,
+    /// Overrides the air jump x velocity calculated from the fighters attributes
+    pub air_jump_x_vel:           Option<f32>,
+    /// Overrides the air jump y velocity calculated from the fighters attributes
+    pub air_jump_y_vel:           Option<f32>,
+    /// The y multiplier of each air jump for fighters whose air jumps differ in height
+    pub air_jump_y_mults:         Vec<f32>,
+    /// Overrides the hitstun multiplier of the selected profile
+    pub hitstun_mult:             Option<f32>,
+    pub power_shield:             PowerShieldSettings,
+}
+
+impl Default for FighterSettings {
+    fn default() -> FighterSettings {
+        FighterSettings {
+            shield:                   ShieldSettings::default(),
+            missed_tech_forced_getup: 220,
+            item_hold_x:              4.0,
+            item_hold_y:              11.0,
+            grab_hold_x:              4.0,
+            grab_hold_y:              11.0,
+            air_jump_x_vel:           None,
+            air_jump_y_vel:           None,
+            air_jump_y_mults:         vec!(),
+            hitstun_mult:             None,
+            power_shield:             PowerShieldSettings::default(),
+        }
+    }
+}
+
+impl FighterSettings {
+    /// Replace the values of the selected profile's mechanics that are overridden for this fighter
+    pub(crate) fn override_mechanics(&self, mechanics: &mut Mechanics) {
+        let power_shield = &mut mechanics.power_shield;
+        power_shield.reflect_window = self.power_shield.reflect_window.unwrap_or(power_shield.reflect_window);
+        power_shield.parry_window   = self.power_shield.parry_window.unwrap_or(power_shield.parry_window);
+        power_shield.parry_duration = self.power_shield.parry_duration.unwrap_or(power_shield.parry_duration);
+        mechanics.hitstun_mult = self.hitstun_mult.unwrap_or(mechanics.hitstun_mult);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct ShieldSettings {
+    pub stick_lock:        bool,
+    pub stick_mult:        f32,
+    /// Overrides the shield x offset calculated from the fighters shield bone
+    pub offset_x:          Option<f32>,
+    /// Overrides the shield y offset calculated from the fighters shield bone
+    pub offset_y:          Option<f32>,
+    /// Shield offset used when the fighter has no shield bone or guard pose
+    pub fallback_offset_x: f32,
+    pub fallback_offset_y: f32,
+    pub hp_scaling:        f32,
+    /// hp_max, hp_regen and hp_cost are scaled by the fighters shield strength
+    pub hp_max:            f32,
+    pub hp_regen:          f32,
+    pub hp_cost:           f32,
+}
+
+impl Default for ShieldSettings {
+    fn default() -> ShieldSettings {
+        ShieldSettings {
+            stick_lock:        false,
+            stick_mult:        3.0,
+            offset_x:          None,
+            offset_y:          None,
+            fallback_offset_x: 1.0,
+            fallback_offset_y: 8.0,
+            hp_scaling:        1.15,
+            hp_max:            60.0,
+            hp_regen:          0.1,
+            hp_cost:           0.28,
+        }
+    }
+}
+
+/// Overrides the power shield values of the selected profile
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct PowerShieldSettings {
+    pub reflect_window: Option<u64>,
+    pub parry_window:   Option<u64>,
+    pub parry_duration: Option<u64>,
+}
+
+impl Settings {
+    /// Load the settings file at path, if path is None the built-in defaults are used for every fighter
+    pub(crate) fn load(path: Option<&Path>) -> Result<Settings, ExportError> {
+        let path = if let Some(path) = path {
+            path
+        } else {
+            return Ok(Settings { global: FighterSettings::default(), fighters: HashMap::new(), custom_profile: Mechanics::default() });
+        };
+
+        let error = |reason: String| ExportError::Settings (format!("{}: {}", path.to_string_lossy(), reason));
+        let text = fs::read_to_string(path).map_err(|err| error(err.to_string()))?;
+        let mut file = match text.parse::<Value>().map_err(|err| error(err.to_string()))? {
+            Value::Table (table) => table,
+            _ => return Err(error(String::from("expected a table"))),
+        };
+
+        let defaults = Value::try_from(FighterSettings::default()).unwrap();
+        let global_overrides = file.remove("global").unwrap_or_else(|| Value::Table (Default::default()));
+        let global_value = merge(defaults, global_overrides);
+
+        let mut fighters = HashMap::new();
+        if let Some(fighter_tables) = file.remove("fighters") {
+            let fighter_tables = match fighter_tables {
+                Value::Table (table) => table,
+                _ => return Err(error(String::from("fighters needs to be a table"))),
+            };
+            for (name, overrides) in fighter_tables {
+                let settings: FighterSettings = merge(global_value.clone(), overrides).try_into()
+                    .map_err(|err| error(format!("fighters.{}: {}", name, err)))?;
+                fighters.insert(name.to_lowercase(), settings);
+            }
+        }
+
+        let custom_profile = match file.remove("custom_profile") {
+            Some(overrides) => merge(Value::try_from(Mechanics::default()).unwrap(), overrides).try_into()
+                .map_err(|err| error(format!("custom_profile: {}", err)))?,
+            None => Mechanics::default(),
+        };
+
+        if let Some(key) = file.keys().next() {
+            return Err(error(format!("unknown section {}", key)));
+        }
+
+        let global = global_value.try_into().map_err(|err| error(format!("global: {}", err)))?;
+        Ok(Settings { global, fighters, custom_profile })
+    }
+
+    /// The settings for the fighter with the given cased name
+    pub(crate) fn fighter(&self, name: &str) -> &FighterSettings {
+        self.fighters.get(&name.to_lowercase()).unwrap_or(&self.global)
+    }
+}
+
+/// Recursively replace values in base with values in overrides
+fn merge(base: Value, overrides: Value) -> Value {
+    match (base, overrides) {
+        (Value::Table (mut base), Value::Table (overrides)) => {
+            for (key, value) in overrides {
+                let merged = match base.remove(&key) {
+                    Some(base_value) => merge(base_value, value),
+                    None             => value,
+                };
+                base.insert(key, merged);
+            }
+            Value::Table (base)
+        }
+        (_, overrides) => overrides,
+    }
+}
@@ -0,0 +1,53 @@
+use brawllib_rs::fighter::Fighter as BrawlFighter;
+use brawllib_rs::high_level_fighter::HighLevelFighter;
+use pf_sandbox_lib::fighter::Shield;
+
+use crate::bones::BoneChain;
+use crate::settings::FighterSettings;
+use crate::warnings::Warnings;
+
+/// Bone the shield is centered on, the hip is near the center of every fighters body
+const SHIELD_BONE: &str = "HipN";
+
+/// Creates the fighters shield from its attributes and the position of its shield bone while shielding.
+///
+/// The position of the shield bone on the first frame of the Guard subaction is found with a BoneChain as it may not have a hurtbox.
+/// The shield hp is the default hp scaled by the fighters shield strength.
+/// Brawl shields regenerate and deplete at the same rate for every fighter relative to their hp,
+/// so the regen and cost are scaled by the shield strength too, keeping the time to deplete and regenerate the shield the same.
+pub(crate) fn shield(brawl_fighter: &BrawlFighter, hl_fighter: &HighLevelFighter, settings: &FighterSettings, warnings: &mut Warnings) -> Shield {
+    let attributes = &hl_fighter.attributes;
+    let shield_settings = &settings.shield;
+
+    let (center_x, center_y) = guard_position(brawl_fighter, hl_fighter).unwrap_or_else(|| {
+        warnings.fighter(format!("no {} bone in the Guard subaction, using the fallback shield offset", SHIELD_BONE));
+        (shield_settings.fallback_offset_x, shield_settings.fallback_offset_y)
+    });
+
+    let strength = if attributes.shield_strength.is_finite() && attributes.shield_strength > 0.0 {
+        attributes.shield_strength
+    } else {
+        warnings.fighter(format!("invalid shield strength {}, using 1.0", attributes.shield_strength));
+        1.0
+    };
+
+    Shield {
+        stick_lock: shield_settings.stick_lock,
+        stick_mult: shield_settings.stick_mult,
+        offset_x:   shield_settings.offset_x.unwrap_or(center_x),
+        offset_y:   shield_settings.offset_y.unwrap_or(center_y),
+        break_vel:  attributes.shield_break_vel,
+        scaling:    attributes.shield_size,
+        hp_scaling: shield_settings.hp_scaling,
+        hp_max:     shield_settings.hp_max * strength,
+        hp_regen:   shield_settings.hp_regen * strength,
+        hp_cost:    shield_settings.hp_cost * strength,
+    }
+}
+
+/// The position of the shield bone on the first frame of the Guard subaction
+fn guard_position(brawl_fighter: &BrawlFighter, hl_fighter: &HighLevelFighter) -> Option<(f32, f32)> {
+    let guard = hl_fighter.subactions.iter().find(|x| x.name == "Guard")?;
+    let frame = guard.frames.get(0)?;
+    BoneChain::new(brawl_fighter, SHIELD_BONE)?.position(&frame.hurt_boxes)
+}
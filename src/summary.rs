@@ -0,0 +1,58 @@
+/// Records what happened to every fighter during an export so it can be displayed at the end of the run
+pub(crate) struct ExportSummary {
+    entries:   Vec<SummaryEntry>,
+    /// Explicitly named fighters that were not found
+    unmatched: Vec<String>,
+}
+
+struct SummaryEntry {
+    name:   String,
+    status: FighterStatus,
+}
+
+pub(crate) enum FighterStatus {
+    /// Contains the number of conversion warnings
+    Exported (usize),
+    Skipped (String),
+    Failed (String),
+}
+
+impl ExportSummary {
+    pub(crate) fn new() -> ExportSummary {
+        ExportSummary { entries: vec!(), unmatched: vec!() }
+    }
+
+    pub(crate) fn push_unmatched(&mut self, name: String) {
+        self.unmatched.push(name);
+    }
+
+    pub(crate) fn push(&mut self, name: &str, status: FighterStatus) {
+        self.entries.push(SummaryEntry { name: name.to_string(), status });
+    }
+
+    pub(crate) fn print(&self) {
+        let name_width = self.entries.iter().map(|x| x.name.len()).max().unwrap_or(0).max("Fighter".len());
+
+        println!("{:<width$}  {:<8}  {}", "Fighter", "Status", "Reason", width = name_width);
+        for entry in &self.entries {
+            let (status, reason) = match &entry.status {
+                FighterStatus::Exported (0)        => ("exported", String::new()),
+                FighterStatus::Exported (warnings) => ("exported", format!("{} conversion warnings, run the report command for details", warnings)),
+                FighterStatus::Skipped (reason)    => ("skipped", reason.clone()),
+                FighterStatus::Failed (reason)     => ("failed", reason.clone()),
+            };
+            println!("{:<width$}  {:<8}  {}", entry.name, status, reason, width = name_width);
+        }
+
+        let count = |f: fn(&FighterStatus) -> bool| self.entries.iter().filter(|x| f(&x.status)).count();
+        println!(
+            "{} exported, {} skipped, {} failed",
+            count(|x| if let FighterStatus::Exported (_) = x { true } else { false }),
+            count(|x| if let FighterStatus::Skipped (_)  = x { true } else { false }),
+            count(|x| if let FighterStatus::Failed (_)   = x { true } else { false }),
+        );
+        for name in &self.unmatched {
+            println!("{} does not match any fighter", name);
+        }
+    }
+}
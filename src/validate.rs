@@ -0,0 +1,91 @@
+use pf_sandbox_lib::fighter::{Action, Fighter, RenderOrder};
+use num_traits::{FromPrimitive, ToPrimitive};
+
+use crate::cli::DataArgs;
+use crate::export::{self, ExportError};
+use crate::filter::FighterFilter;
+use crate::profile::Profile;
+use crate::settings::Settings;
+
+/// Convert all fighters selected by filter without saving and check that PF Sandbox can use the result
+pub(crate) fn validate(data: &DataArgs, settings: &Settings, profile: &Profile, jobs: Option<usize>, filter: &FighterFilter) -> Result<(), ExportError> {
+    let mut conversion = export::convert(data, settings, profile, jobs, filter)?;
+    conversion.summary.print();
+    conversion.require_fighters()?;
+
+    let mut problem_count = 0;
+    for converted in &conversion.fighters {
+        for problem in validate_fighter(&converted.fighter) {
+            println!("{}: {}", converted.name, problem);
+            problem_count += 1;
+        }
+    }
+
+    if problem_count == 0 {
+        println!("No problems found");
+        Ok(())
+    } else {
+        Err(ExportError::ValidationFailed (problem_count))
+    }
+}
+
+fn validate_fighter(fighter: &Fighter) -> Vec<String> {
+    let mut problems = vec!();
+
+    let idle = Action::Idle.to_usize().unwrap();
+    if fighter.actions[idle].frames.len() == 0 {
+        problems.push(String::from("Idle action has no frames"));
+    }
+
+    for (action_i, action) in fighter.actions.iter().enumerate() {
+        let action_name = Action::from_usize(action_i).map(|x| format!("{:?}", x)).unwrap_or_else(|| action_i.to_string());
+        for (frame_i, frame) in action.frames.iter().enumerate() {
+            let mut problem = |description: String| problems.push(format!("{} frame {}: {}", action_name, frame_i, description));
+
+            let ecb = &frame.ecb;
+            if !(ecb.left.is_finite() && ecb.right.is_finite() && ecb.top.is_finite() && ecb.bottom.is_finite()) {
+                problem(String::from("ECB contains a non finite value"));
+            }
+            else if ecb.left >= ecb.right || ecb.bottom >= ecb.top {
+                problem(format!("ECB is inverted left: {} right: {} top: {} bottom: {}", ecb.left, ecb.right, ecb.top, ecb.bottom));
+            }
+
+            if let Some(ledge_grab_box) = &frame.ledge_grab_box {
+                let values = [ledge_grab_box.x1, ledge_grab_box.y1, ledge_grab_box.x2, ledge_grab_box.y2];
+                if !values.iter().all(|x| x.is_finite()) {
+                    problem(String::from("ledge grab box contains a non finite value"));
+                }
+                else if ledge_grab_box.x1 >= ledge_grab_box.x2 || ledge_grab_box.y1 >= ledge_grab_box.y2 {
+                    problem(format!("ledge grab box is inverted x1: {} y1: {} x2: {} y2: {}", ledge_grab_box.x1, ledge_grab_box.y1, ledge_grab_box.x2, ledge_grab_box.y2));
+                }
+            }
+
+            for (colbox_i, colbox) in frame.colboxes.iter().enumerate() {
+                if !colbox.point.0.is_finite() || !colbox.point.1.is_finite() {
+                    problem(format!("colbox {} has a non finite position", colbox_i));
+                }
+                if !colbox.radius.is_finite() || colbox.radius <= 0.0 {
+                    problem(format!("colbox {} has an invalid radius {}", colbox_i, colbox.radius));
+                }
+            }
+
+            for (link_i, link) in frame.colbox_links.iter().enumerate() {
+                if link.one >= frame.colboxes.len() || link.two >= frame.colboxes.len() {
+                    problem(format!("colbox link {} refers to a colbox that does not exist", link_i));
+                }
+            }
+
+            for render in &frame.render_order {
+                let valid = match render {
+                    RenderOrder::Colbox (i) => *i < frame.colboxes.len(),
+                    RenderOrder::Link (i)   => *i < frame.colbox_links.len(),
+                };
+                if !valid {
+                    problem(String::from("render order refers to an element that does not exist"));
+                }
+            }
+        }
+    }
+
+    problems
+}
@@ -0,0 +1,47 @@
+/// Data that was lost or approximated while converting a fighter.
+/// Identical warnings from the same subaction are combined, e.g. an unsupported hitbox that is active for multiple frames.
+#[derive(Default)]
+pub(crate) struct Warnings {
+    warnings: Vec<Warning>,
+}
+
+pub(crate) struct Warning {
+    /// None when the warning applies to the entire fighter
+    pub subaction: Option<String>,
+    pub message:   String,
+    /// How many times the warning occurred
+    pub count:     usize,
+}
+
+impl Warnings {
+    pub(crate) fn new() -> Warnings {
+        Warnings::default()
+    }
+
+    /// Record a warning about the entire fighter
+    pub(crate) fn fighter(&mut self, message: String) {
+        self.push(None, message);
+    }
+
+    /// Record a warning about a single subaction
+    pub(crate) fn subaction(&mut self, subaction: &str, message: String) {
+        self.push(Some(subaction), message);
+    }
+
+    fn push(&mut self, subaction: Option<&str>, message: String) {
+        let existing = self.warnings.iter_mut().find(|x| x.subaction.as_ref().map(|x| x.as_str()) == subaction && x.message == message);
+        if let Some(warning) = existing {
+            warning.count += 1;
+        } else {
+            self.warnings.push(Warning { subaction: subaction.map(|x| x.to_string()), message, count: 1 });
+        }
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Warning> {
+        self.warnings.iter()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.warnings.len()
+    }
+}